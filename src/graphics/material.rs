@@ -1,4 +1,4 @@
-use crate::graphics::{ShaderUniform, camera::Camera2D, shader::Shader, transform::Transform};
+use crate::graphics::{ShaderUniform, shader::Shader};
 
 pub const TRANSFORM_UNIFORM: &str = "uModel";
 pub const PROJECTION_UNIFORM: &str = "uProjection";
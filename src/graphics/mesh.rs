@@ -13,6 +13,7 @@ const F32_SIZE: u32 = size_of::<f32>() as u32;
 const U32_SIZE: u32 = size_of::<u32>() as u32;
 const BASE_STRIDE: u32 = POSITION_SIZE * F32_SIZE + COLOR_SIZE * F32_SIZE;
 
+#[derive(Default)]
 pub struct Mesh {
     vao: u32,
     vbo: u32,
@@ -149,23 +150,6 @@ impl Mesh {
     }
 }
 
-impl Default for Mesh {
-    fn default() -> Self {
-        Self {
-            vao: 0,
-            vbo: 0,
-            ebo: 0,
-
-            vertices:   Vec::new(),
-            indices:    Vec::new(),
-            attributes: Vec::new(),
-            stride:     0,
-
-            build: false,
-        }
-    }
-}
-
 impl Drop for Mesh {
     fn drop(&mut self) {
         if !self.build {
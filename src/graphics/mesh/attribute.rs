@@ -42,10 +42,6 @@ pub struct VertexAttribute {
 
 impl VertexAttribute {
     pub fn new(attribute_type: &AttributeType) -> Self {
-        Self {
-            name:   attribute_type.name().to_string(),
-            size:   attribute_type.size(),
-            __type: attribute_type.clone(),
-        }
+        Self { name: attribute_type.name().to_string(), size: attribute_type.size(), __type: *attribute_type }
     }
 }
@@ -94,6 +94,14 @@ impl Instance {
         self.update = true;
     }
 
+    /// Removes the instance at `index` in O(1) by moving the last instance into its slot.
+    pub fn swap_remove_instance(&mut self, index: usize) {
+        assert!(index < self.instances.len(), "Index out of bounds for instance data.");
+
+        self.instances.swap_remove(index);
+        self.update = true;
+    }
+
     pub fn get_instance(&self, index: usize) -> Option<&InstanceData> {
         if index < self.instances.len() { Some(&self.instances[index]) } else { None }
     }
@@ -1,20 +1,24 @@
 mod camera;
-mod color;
 mod material;
 mod mesh;
+mod sandbox_renderer;
 mod shader;
 mod transform;
+#[cfg(feature = "window")]
 mod window;
 
 pub use camera::Camera2D;
-pub use color::Color;
 pub use material::{Material, PROJECTION_UNIFORM, TRANSFORM_UNIFORM};
 pub use mesh::{
     Mesh,
     attribute::{AttributeType, VertexAttribute, positions_from_vec3s},
-    instance::{Instance, InstanceData},
+    instance::{Instance, InstanceData, RingBufferInstance, StorageBufferInstance},
     vertex::Vertex,
 };
+pub use sandbox_renderer::SandboxRenderer;
 pub use shader::{Shader, ShaderUniform};
 pub use transform::Transform;
+#[cfg(feature = "window")]
 pub use window::{Samples, Window, WindowConfig, WindowMode};
+
+pub use crate::utils::Color;
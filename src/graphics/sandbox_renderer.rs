@@ -0,0 +1,121 @@
+use glam::{Vec2, Vec3};
+use hashbrown::HashMap;
+
+use crate::{
+    graphics::{Instance, InstanceData, Transform},
+    sandbox::{Cell, CellChange, GridPos, Materials, Sandbox},
+};
+
+pub const GRID_SIZE: f32 = 32.0;
+
+/// Mirrors the cells of a [`Sandbox`] into an instanced mesh by consuming its recorded changes. Particles are drawn
+/// from a second instanced mesh that is rewritten on every sync.
+#[derive(Debug)]
+pub struct SandboxRenderer {
+    mesh_instance:     Instance,
+    particle_instance: Instance,
+    indices:           HashMap<GridPos, usize>,
+    positions:         Vec<GridPos>,
+}
+
+impl SandboxRenderer {
+    pub fn new(mesh_instance: Instance, particle_instance: Instance, sandbox: &mut Sandbox) -> Self {
+        let mut renderer = Self { mesh_instance, particle_instance, indices: HashMap::new(), positions: Vec::new() };
+
+        sandbox.track_changes();
+        sandbox.take_changes(); // The full sync below already covers anything recorded so far
+        for (pos, cell) in sandbox.cells() {
+            renderer.insert(pos, cell, sandbox.materials());
+        }
+
+        renderer
+    }
+
+    pub fn grid_pos_from_world_pos(world_pos: Vec3) -> GridPos {
+        let x = Self::to_grid_coord(world_pos.x);
+        let y = Self::to_grid_coord(world_pos.y);
+        (x, y)
+    }
+
+    pub fn sync(&mut self, sandbox: &mut Sandbox) {
+        let changes = sandbox.take_changes();
+        let materials = sandbox.materials();
+        for change in changes {
+            match change {
+                CellChange::Inserted(pos, cell) => self.insert(pos, &cell, materials),
+                CellChange::Removed(pos) => self.remove(&pos),
+                CellChange::Moved(from, to) => self.move_to(&from, to),
+                CellChange::Updated(pos, cell) => {
+                    if let Some(&idx) = self.indices.get(&pos) {
+                        self.mesh_instance.update_instance_color(idx, &materials.color(&cell));
+                    }
+                }
+            }
+        }
+        self.sync_particles(sandbox);
+    }
+
+    pub fn draw(&mut self) {
+        self.mesh_instance.draw();
+        self.particle_instance.draw();
+    }
+
+    // ----------------< Private >----------------
+    fn insert(&mut self, pos: GridPos, cell: &Cell, materials: &Materials) {
+        if self.indices.contains_key(&pos) {
+            self.remove(&pos);
+        }
+        let idx = self.mesh_instance.add_instance(InstanceData::new(Self::transform(&pos), &materials.color(cell)));
+        self.indices.insert(pos, idx);
+        self.positions.push(pos);
+    }
+
+    fn remove(&mut self, pos: &GridPos) {
+        let Some(idx) = self.indices.remove(pos) else {
+            return;
+        };
+        self.mesh_instance.swap_remove_instance(idx);
+        self.positions.swap_remove(idx);
+        if let Some(&moved) = self.positions.get(idx) {
+            self.indices.insert(moved, idx); // The last instance now lives in the freed slot
+        }
+    }
+
+    fn move_to(&mut self, from: &GridPos, to: GridPos) {
+        let Some(idx) = self.indices.remove(from) else {
+            return;
+        };
+        self.indices.insert(to, idx);
+        self.positions[idx] = to;
+        self.mesh_instance.update_instance_transform(idx, Self::transform(&to));
+    }
+
+    fn sync_particles(&mut self, sandbox: &Sandbox) {
+        let particles = sandbox.particles();
+        for (idx, particle) in particles.iter().enumerate() {
+            let (transform, color) =
+                (Self::particle_transform(particle.pos), sandbox.materials().color(&particle.cell));
+            if idx < self.particle_instance.instance_count() {
+                self.particle_instance.update_instance_transform(idx, transform);
+                self.particle_instance.update_instance_color(idx, &color);
+            } else {
+                self.particle_instance.add_instance(InstanceData::new(transform, &color));
+            }
+        }
+        while self.particle_instance.instance_count() > particles.len() {
+            self.particle_instance.swap_remove_instance(self.particle_instance.instance_count() - 1);
+        }
+    }
+
+    fn transform(pos: &GridPos) -> Transform {
+        Self::particle_transform(Vec2::new(pos.0 as f32, pos.1 as f32))
+    }
+
+    fn particle_transform(pos: Vec2) -> Transform {
+        Transform::from_translation((pos * GRID_SIZE).extend(0.0)).with_scale(Vec3::splat(GRID_SIZE))
+    }
+
+    fn to_grid_coord(value: f32) -> isize {
+        if value < 0.0 { (value / GRID_SIZE - 0.5) as isize } else { (value / GRID_SIZE + 0.5) as isize }
+    }
+}
@@ -78,8 +78,7 @@ impl Transform {
     }
 
     pub fn matrix(&self) -> Mat4 {
-        let matrix = Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation);
-        matrix
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
     }
 
     #[rustfmt::skip]
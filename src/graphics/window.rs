@@ -1,5 +1,4 @@
 use glad_gl::gl;
-use glam::UVec2;
 use glfw::Context;
 use log::{debug, error, info};
 
@@ -107,7 +106,7 @@ impl Window {
     }
 
     pub fn events(&mut self) -> Vec<glfw::WindowEvent> {
-        glfw::flush_messages(&mut self.events).map(|(_, event)| event).collect()
+        glfw::flush_messages(&self.events).map(|(_, event)| event).collect()
     }
 
     pub fn set_clear_color(&self, color: Color) {
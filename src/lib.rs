@@ -0,0 +1,3 @@
+pub mod graphics;
+pub mod sandbox;
+pub mod utils;
@@ -1,15 +1,8 @@
-mod graphics;
-mod sandbox;
-mod utils;
-
 use std::{cell::RefCell, rc::Rc};
 
+use falling_sand::{graphics::*, sandbox::*, utils::flatten};
 use glam::{Vec2, Vec3};
-use graphics::*;
-use log::error;
-use sandbox::*;
-
-use crate::utils::flatten;
+use log::{debug, error, info};
 
 #[rustfmt::skip]
 const QUAD_VERTICES: [[f32; 3]; 4] = [
@@ -19,6 +12,10 @@ const QUAD_VERTICES: [[f32; 3]; 4] = [
     [-0.5,  0.5, 0.0],
 ];
 const QUAD_INDICES: [u32; 6] = [0, 1, 3, 1, 2, 3];
+const EXPLOSION_RADIUS: f32 = 8.0; // Blast set off at the cursor with X
+const EXPLOSION_FORCE: f32 = 30.0;
+const FIELD_RADIUS: f32 = 16.0; // Anti-gravity field placed at the cursor with F
+const WORLD_SIZE: isize = 256; // Walled box toggled with B
 
 fn main() {
     env_logger::Builder::from_default_env().filter_level(log::LevelFilter::Debug).init();
@@ -36,15 +33,34 @@ fn main() {
     let mut camera =
         Camera2D { viewport: Vec2::new(w_config.width as f32, w_config.height as f32), ..Default::default() };
 
-    let instance = Mesh::new()
-        .with_attribute(AttributeType::Position, flatten(QUAD_VERTICES))
-        .with_attribute(AttributeType::Color, flatten([Color::WHITE; 4]))
-        .with_indices(QUAD_INDICES.to_vec())
-        .create_instance(100);
+    let quad = || {
+        Mesh::new()
+            .with_attribute(AttributeType::Position, flatten(QUAD_VERTICES))
+            .with_attribute(AttributeType::Color, flatten([Color::WHITE; 4]))
+            .with_indices(QUAD_INDICES.to_vec())
+    };
+    let instance = quad().create_instance(100);
+    let particle_instance = quad().create_instance(100);
 
     let material = Material::new(Shader::instance());
 
-    let sandbox = Rc::new(RefCell::new(Sandbox::new(instance)));
+    let materials = match Materials::load("assets/materials.toml") {
+        Ok(materials) => materials,
+        Err(e) => {
+            error!("Failed to load materials: {e}");
+            return;
+        }
+    };
+
+    let sandbox = match std::env::var("SANDBOX_SEED").ok().and_then(|seed| seed.parse().ok()) {
+        Some(seed) => Sandbox::with_seed(seed),
+        None => Sandbox::new(),
+    }
+    .with_materials(materials);
+    info!("Sandbox seed: {}", sandbox.seed());
+
+    let sandbox = Rc::new(RefCell::new(sandbox));
+    let mut renderer = SandboxRenderer::new(instance, particle_instance, &mut sandbox.borrow_mut());
 
     let mut brush = Brush::new(Rc::clone(&sandbox));
 
@@ -108,55 +124,111 @@ fn main() {
                 glfw::WindowEvent::Scroll(_x_offset, y_offset) => {
                     camera.zoom *= (1.0 + y_offset * 0.1) as f32;
                 }
-                glfw::WindowEvent::Key(key, _scancode, action, _modifiers) => {
-                    if action == glfw::Action::Press {
-                        match key {
-                            glfw::Key::Escape => window.close(),
-                            glfw::Key::Num1 => {
-                                brush.kind = CellKind::Sand;
-                            }
-                            glfw::Key::Num2 => {
-                                brush.kind = CellKind::Stone;
-                            }
-                            glfw::Key::Num3 => {
-                                brush.kind = CellKind::Water;
-                            }
-                            glfw::Key::Q => {
-                                brush.size = brush.size.previous();
-                            }
-                            glfw::Key::E => {
-                                brush.size = brush.size.next();
-                            }
-                            _ => {}
+                glfw::WindowEvent::Key(key, _scancode, glfw::Action::Press, modifiers) => match key {
+                    glfw::Key::Escape => window.close(),
+                    glfw::Key::Q => {
+                        brush.size = brush.size.previous();
+                    }
+                    glfw::Key::E => {
+                        brush.size = brush.size.next();
+                    }
+                    glfw::Key::X => {
+                        let world_pos = get_world_position(&camera, cursor_pos);
+                        sandbox.borrow_mut().explode(
+                            SandboxRenderer::grid_pos_from_world_pos(world_pos),
+                            EXPLOSION_RADIUS,
+                            EXPLOSION_FORCE,
+                        );
+                    }
+                    glfw::Key::B => {
+                        let mut sandbox = sandbox.borrow_mut();
+                        let bounds = match sandbox.bounds() {
+                            Some(_) => None,
+                            None => Some(Bounds::new(
+                                (-WORLD_SIZE / 2, -WORLD_SIZE / 2),
+                                (WORLD_SIZE / 2, WORLD_SIZE / 2),
+                                Edge::Wall,
+                            )),
+                        };
+                        sandbox.set_bounds(bounds);
+                    }
+                    glfw::Key::G => {
+                        let mut sandbox = sandbox.borrow_mut();
+                        let gravity = sandbox.gravity().perp();
+                        sandbox.set_gravity(gravity);
+                        info!("Gravity: {gravity}");
+                    }
+                    glfw::Key::F => {
+                        let mut sandbox = sandbox.borrow_mut();
+                        let pos = SandboxRenderer::grid_pos_from_world_pos(get_world_position(&camera, cursor_pos));
+                        let shape =
+                            FieldShape::Circle { center: Vec2::new(pos.0 as f32, pos.1 as f32), radius: FIELD_RADIUS };
+                        let gravity = -sandbox.gravity();
+                        sandbox.add_force_field(ForceField::new(shape, FieldEffect::Gravity(gravity)));
+                    }
+                    glfw::Key::Tab => {
+                        let sandbox = sandbox.borrow();
+                        let materials = sandbox.materials();
+                        let current = materials.kinds().position(|kind| kind == brush.kind).unwrap_or(0);
+                        let step = if modifiers.contains(glfw::Modifiers::Shift) { materials.len() - 1 } else { 1 };
+                        if let Some(kind) = materials.kinds().nth((current + step) % materials.len()) {
+                            brush.kind = kind;
+                            info!("Selected material: {}", materials[kind].name);
                         }
                     }
-                }
+                    key => {
+                        let sandbox = sandbox.borrow();
+                        if let Some(kind) = material_hotkey(key).and_then(|n| sandbox.materials().kinds().nth(n)) {
+                            brush.kind = kind;
+                            info!("Selected material: {}", sandbox.materials()[kind].name);
+                        }
+                    }
+                },
                 _ => {}
             }
         }
         if mouse_pressed[0] {
             let world_pos = get_world_position(&camera, cursor_pos);
-            // sandbox.insert_cell(Sandbox::grid_pos_from_world_pos(world_pos), current_kind);
-            brush.spawn(Sandbox::grid_pos_from_world_pos(world_pos));
+            // sandbox.insert_cell(SandboxRenderer::grid_pos_from_world_pos(world_pos), current_kind);
+            brush.spawn(SandboxRenderer::grid_pos_from_world_pos(world_pos));
         }
 
         if mouse_pressed[1] {
             let world_pos = get_world_position(&camera, cursor_pos);
-            // sandbox.remove_cell(Sandbox::grid_pos_from_world_pos(world_pos));
-            brush.remove(Sandbox::grid_pos_from_world_pos(world_pos));
+            // sandbox.remove_cell(SandboxRenderer::grid_pos_from_world_pos(world_pos));
+            brush.remove(SandboxRenderer::grid_pos_from_world_pos(world_pos));
         }
 
         window.clear();
 
-        sandbox.borrow_mut().update(dt);
+        let report = sandbox.borrow_mut().update(dt);
+        if report.skipped > 0 {
+            debug!("Simulation fell behind, dropped {} ticks", report.skipped);
+        }
 
         material.apply(&[(PROJECTION_UNIFORM, ShaderUniform::Mat4(camera.projection_matrix().to_cols_array()))]);
-        sandbox.borrow_mut().draw();
+        renderer.sync(&mut sandbox.borrow_mut());
+        renderer.draw();
 
         window.swap_buffers();
     }
 }
 
+fn material_hotkey(key: glfw::Key) -> Option<usize> {
+    match key {
+        glfw::Key::Num1 => Some(0),
+        glfw::Key::Num2 => Some(1),
+        glfw::Key::Num3 => Some(2),
+        glfw::Key::Num4 => Some(3),
+        glfw::Key::Num5 => Some(4),
+        glfw::Key::Num6 => Some(5),
+        glfw::Key::Num7 => Some(6),
+        glfw::Key::Num8 => Some(7),
+        glfw::Key::Num9 => Some(8),
+        _ => None,
+    }
+}
+
 fn get_world_position(camera: &Camera2D, screen_pos: Vec2) -> Vec3 {
     let ndc_x = (screen_pos.x / camera.viewport.x) * 2.0 - 1.0;
     let ndc_y = 1.0 - (screen_pos.y / camera.viewport.y) * 2.0;
@@ -0,0 +1,135 @@
+use hashbrown::HashSet;
+
+use crate::sandbox::{
+    cell::{Cell, CellKind},
+    chunk::ChunkGrid,
+    sandbox::GridPos,
+};
+
+const SEAM_DEPTH: isize = 16; // Rows mirrored behind a wrapping edge, enough for the furthest sideways flow
+
+/// Stand-in cell for the space beyond a wall, seen by moving cells like an indestructible solid.
+pub static WALL: Cell = Cell::new(CellKind::BOUNDARY, 0);
+
+/// What happens to cells reaching an edge of a bounded world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Edge {
+    /// Cells collide with it like with an indestructible solid.
+    #[default]
+    Wall,
+    /// Cells leaving through it are removed.
+    Void,
+    /// Cells leaving through it come back in at the opposite edge.
+    Wrap,
+}
+
+/// Where a position ends up in a bounded world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Place {
+    /// Inside the bounds, positions beyond wrapping edges are moved back in.
+    Inside(GridPos),
+    Wall,
+    Void,
+}
+
+/// Rectangular extent of the world with the behaviour of each of its edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bounds {
+    /// Lowest position inside the world.
+    pub min:    GridPos,
+    /// Position just past the highest one inside the world.
+    pub max:    GridPos,
+    pub left:   Edge,
+    pub right:  Edge,
+    pub bottom: Edge,
+    pub top:    Edge,
+}
+
+impl Bounds {
+    /// Bounds from `min` up to but excluding `max` with the same behaviour on every edge.
+    pub fn new(min: GridPos, max: GridPos, edge: Edge) -> Self {
+        assert!(min.0 < max.0 && min.1 < max.1, "Bounds need a positive size.");
+        Self { min, max, left: edge, right: edge, bottom: edge, top: edge }
+    }
+
+    pub fn contains(&self, pos: GridPos) -> bool {
+        (self.min.0..self.max.0).contains(&pos.0) && (self.min.1..self.max.1).contains(&pos.1)
+    }
+
+    /// Resolves `pos` against the edges it lies beyond. Walls win over voids at the corners.
+    pub fn place(&self, pos: GridPos) -> Place {
+        let x = Self::crossed(pos.0, self.min.0, self.max.0, self.left, self.right);
+        let y = Self::crossed(pos.1, self.min.1, self.max.1, self.bottom, self.top);
+        match (x, y) {
+            (Some(Edge::Wall), _) | (_, Some(Edge::Wall)) => Place::Wall,
+            (Some(Edge::Void), _) | (_, Some(Edge::Void)) => Place::Void,
+            _ => Place::Inside((Self::wrap(pos.0, self.min.0, self.max.0), Self::wrap(pos.1, self.min.1, self.max.1))),
+        }
+    }
+
+    /// Whether `pos` lies beyond a wall.
+    pub fn walled(&self, pos: GridPos) -> bool {
+        self.place(pos) == Place::Wall
+    }
+
+    /// The positions beyond each wrapping edge, up to `SEAM_DEPTH` deep, paired with the ones they wrap to.
+    fn seam(&self) -> impl Iterator<Item = (GridPos, GridPos)> + '_ {
+        let (width, height) = (self.max.0 - self.min.0, self.max.1 - self.min.1);
+        let columns = (self.min.1..self.max.1).flat_map(move |y| {
+            (1..=SEAM_DEPTH.min(width)).flat_map(move |depth| {
+                let left = (self.left == Edge::Wrap).then_some(((self.min.0 - depth, y), (self.max.0 - depth, y)));
+                let right =
+                    (self.right == Edge::Wrap).then_some(((self.max.0 - 1 + depth, y), (self.min.0 - 1 + depth, y)));
+                left.into_iter().chain(right)
+            })
+        });
+        let rows = (self.min.0..self.max.0).flat_map(move |x| {
+            (1..=SEAM_DEPTH.min(height)).flat_map(move |depth| {
+                let bottom = (self.bottom == Edge::Wrap).then_some(((x, self.min.1 - depth), (x, self.max.1 - depth)));
+                let top =
+                    (self.top == Edge::Wrap).then_some(((x, self.max.1 - 1 + depth), (x, self.min.1 - 1 + depth)));
+                bottom.into_iter().chain(top)
+            })
+        });
+        columns.chain(rows)
+    }
+
+    // ----------------< Private >----------------
+    fn crossed(value: isize, min: isize, max: isize, low: Edge, high: Edge) -> Option<Edge> {
+        if value < min {
+            Some(low)
+        } else if value >= max {
+            Some(high)
+        } else {
+            None
+        }
+    }
+
+    fn wrap(value: isize, min: isize, max: isize) -> isize {
+        min + (value - min).rem_euclid(max - min)
+    }
+}
+
+/// The bounds of a world as moving cells see them during a tick. Regions only hold the chunks around them, so the
+/// cells behind a wrapping edge are mirrored in from the opposite side when the tick starts.
+#[derive(Debug, Clone, Default)]
+pub struct Edges {
+    bounds: Option<Bounds>,
+    seam:   HashSet<GridPos>,
+}
+
+impl Edges {
+    pub fn new(bounds: Option<Bounds>, grid: &ChunkGrid) -> Self {
+        let seam = match &bounds {
+            Some(bounds) => bounds.seam().filter(|(_, wrapped)| grid.contains(wrapped)).map(|(pos, _)| pos).collect(),
+            None => HashSet::new(),
+        };
+        Self { bounds, seam }
+    }
+
+    /// Whether `pos` is taken for cells moving into it: beyond a wall, or beyond a wrapping edge with the spot it
+    /// wraps to taken.
+    pub fn blocked(&self, pos: GridPos) -> bool {
+        self.bounds.is_some_and(|bounds| bounds.walled(pos)) || self.seam.contains(&pos)
+    }
+}
@@ -1,11 +1,6 @@
 use std::{cell::RefCell, rc::Rc};
 
-use glam::Vec3;
-
-use crate::{
-    graphics::{Color, Instance, InstanceData, Transform},
-    sandbox::{CellKind, Sandbox, sandbox::GRID_SIZE},
-};
+use crate::sandbox::{CellKind, Sandbox};
 
 pub enum BrushSize {
     Small,
@@ -59,7 +54,7 @@ pub struct Brush {
 
 impl Brush {
     pub fn new(sandbox: Rc<RefCell<Sandbox>>) -> Self {
-        Self { size: BrushSize::Small, kind: CellKind::Sand, sandbox }
+        Self { size: BrushSize::Small, kind: CellKind::default(), sandbox }
     }
 
     pub fn spawn(&mut self, pos: (isize, isize)) {
@@ -1,168 +1,192 @@
-use rand::seq::{IndexedRandom, SliceRandom};
+use glam::{BVec2, Vec2};
+use rand::{Rng, seq::SliceRandom};
 
-use crate::{graphics::Color, sandbox::sandbox::GridPos};
+use crate::sandbox::{
+    force::Orientation,
+    material::{AMBIENT_TEMPERATURE, Materials},
+    region::MAX_TRAVEL,
+    rng::SimRng,
+    sandbox::GridPos,
+};
 
 const SLEEP_THRESHOLD: u32 = 10;
+const GAS_TRAVEL: f32 = 1.0; // Rising speed of gases, they don't build up velocity
+const IMPULSE_THRESHOLD: f32 = 2.0; // Cells running into something at a lower relative speed don't push it
+const IMPULSE_TRANSFER: f32 = 0.5; // Share of the relative velocity handed to a cell that was run into
+const REST_SPEED: f32 = 0.5; // Slower cells on the ground come to a halt
 
-pub struct MovementOptionGroup(&'static [GridPos]);
-pub type CellMovement = &'static [MovementOptionGroup];
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MovementOptionGroup(pub Vec<GridPos>);
 
 impl MovementOptionGroup {
-    pub fn shuffled(&self) -> Vec<GridPos> {
-        let mut shuffled = self.0.to_vec();
-        shuffled.shuffle(&mut rand::rng());
+    pub fn shuffled(&self, rng: &mut SimRng) -> Vec<GridPos> {
+        let mut shuffled = self.0.clone();
+        shuffled.shuffle(rng);
         shuffled
     }
 }
 
-#[rustfmt::skip]
-const SAND_MOVEMENT: CellMovement = &[
-    MovementOptionGroup(&[(0, -1)]),
-    MovementOptionGroup(&[(1, -1), (-1, -1)])
-];
-
-#[rustfmt::skip]
-const WET_SAND_MOVEMENT: CellMovement = &[
-    MovementOptionGroup(&[(0, -1)]),
-];
-
-#[rustfmt::skip]
-const WATER_MOVEMENT: CellMovement = &[
-    MovementOptionGroup(&[(0, -1)]),
-    MovementOptionGroup(&[(1, -1), (-1, -1)]),
-    MovementOptionGroup(&[(1, 0), (-1, 0)]),
-];
-
-const SAND_COLOR: [Color; 5] = [
-    Color::new(0.965, 0.843, 0.690, 1.0),
-    Color::new(0.949, 0.824, 0.663, 1.0),
-    Color::new(0.925, 0.800, 0.635, 1.0),
-    Color::new(0.906, 0.769, 0.588, 1.0),
-    Color::new(0.882, 0.749, 0.573, 1.0),
-];
-
-const WET_SAND_COLOR: [Color; 5] = [
-    Color::new(0.929, 0.694, 0.392, 1.0),
-    Color::new(0.906, 0.678, 0.384, 1.0),
-    Color::new(0.871, 0.659, 0.376, 1.0),
-    Color::new(0.851, 0.631, 0.345, 1.0),
-    Color::new(0.820, 0.616, 0.345, 1.0),
-];
-
-const STONE_COLOR: [Color; 5] = [
-    Color::new(0.313, 0.313, 0.313, 1.0),
-    Color::new(0.345, 0.345, 0.345, 1.0),
-    Color::new(0.392, 0.392, 0.392, 1.0),
-    Color::new(0.254, 0.254, 0.254, 1.0),
-    Color::new(0.196, 0.196, 0.196, 1.0),
-];
-
-const WATER_COLOR: [Color; 5] = [
-    Color::new(0.000, 0.624, 0.784, 1.0),
-    Color::new(0.000, 0.671, 0.843, 1.0),
-    Color::new(0.000, 0.710, 0.894, 1.0),
-    Color::new(0.122, 0.757, 0.918, 1.0),
-    Color::new(0.224, 0.816, 0.969, 1.0),
-];
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum TransitionCondition {
+    /// The moving cell runs into a cell of this kind.
+    Contact(CellKind),
+    /// The cell got hotter than this temperature.
+    Above(f32),
+    /// The cell got colder than this temperature.
+    Below(f32),
+    /// At least `count` cells of `kind` are within `radius` of the cell.
+    Near { kind: CellKind, count: u8, radius: u8 },
+    /// The cell holds more moisture than this.
+    Moist(f32),
+    /// The cell is live or touches a live conductor or a battery.
+    Charged,
+}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum TransitionTarget {
-    This,
-    Other,
+impl TransitionCondition {
+    pub fn is_contact(&self) -> bool {
+        matches!(self, TransitionCondition::Contact(_))
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct CellTransition {
-    condition:  CellKind,
-    pub result: CellKind,
-    pub remove: bool,
-    pub target: TransitionTarget,
+    pub condition: TransitionCondition,
+    /// What this cell turns into.
+    pub into:      Option<CellKind>,
+    /// What the cell it ran into turns into, only for contact reactions.
+    pub other:     Option<CellKind>,
+    /// Deletes this cell after the reaction.
+    pub remove:    bool,
+    /// Spawned into a free spot next to the reaction.
+    pub byproduct: Option<CellKind>,
+    /// Has to be right next to the cell for the reaction to happen, but is not consumed.
+    pub catalyst:  Option<CellKind>,
+    /// Chance per tick once the condition holds.
+    pub chance:    f32,
+    /// Ticks a contact has to last before the reaction may happen.
+    pub duration:  u8,
+    /// Detonates the cell with its material's [`Explosion`].
+    pub explode:   bool,
 }
 
-const SAND_TRANSITIONS: &[CellTransition] = &[CellTransition {
-    condition: CellKind::Water,
-    result:    CellKind::WetSand,
-    remove:    true,
-    target:    TransitionTarget::Other,
-}];
+impl CellTransition {
+    /// Whether the catalyst the transition requires, if any, is next to `pos`.
+    pub fn catalysed<'a, L>(&self, pos: GridPos, lookup: L) -> bool
+    where
+        L: Fn(GridPos) -> Option<&'a Cell>,
+    {
+        self.catalyst.is_none_or(|catalyst| count_neighbours(pos, catalyst, 1, lookup) > 0)
+    }
+}
 
-const WET_SAND_TRANSITIONS: &[CellTransition] = &[];
+/// Counts the cells of `kind` within `radius` around `pos`, not counting the cell at `pos` itself.
+pub fn count_neighbours<'a, L>(pos: GridPos, kind: CellKind, radius: u8, lookup: L) -> usize
+where
+    L: Fn(GridPos) -> Option<&'a Cell>,
+{
+    let radius = radius as isize;
+    let mut count = 0;
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if (dx != 0 || dy != 0) && lookup((pos.0 + dx, pos.1 + dy)).is_some_and(|cell| cell.kind == kind) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
 
-const STONE_TRANSITIONS: &[CellTransition] = &[];
+/// How a flammable material catches fire from a burning neighbour.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Ignition {
+    /// Chance per tick and burning neighbour.
+    pub chance: f32,
+    /// What the burning cell turns into, `None` sets off the material's [`Explosion`] instead.
+    pub into:   Option<CellKind>,
+}
 
-const WATER_TRANSITIONS: &[CellTransition] = &[CellTransition {
-    condition: CellKind::Sand,
-    result:    CellKind::WetSand,
-    remove:    true,
-    target:    TransitionTarget::Other,
-}];
+/// How an explosive material blows up.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Explosion {
+    pub radius: f32,
+    /// Blast strength at the center, fading out towards the radius.
+    pub force:  f32,
+    /// Impact speed that sets the material off when it hits or gets hit by something.
+    pub crush:  Option<f32>,
+}
 
-#[repr(u8)]
+/// Limited lifetime of a material, e.g. fire burning out.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum CellKind {
-    Sand,
-    WetSand,
-    Stone,
-    Water,
+pub struct Lifetime {
+    /// Lifetime range in ticks, rolled for every new cell.
+    pub ticks: (u16, u16),
+    /// What the cell turns into when its time is up, `None` removes it.
+    pub into:  Option<CellKind>,
 }
 
-impl CellKind {
-    pub fn color(&self) -> &Color {
-        match self {
-            CellKind::Sand => &SAND_COLOR,
-            CellKind::WetSand => &WET_SAND_COLOR,
-            CellKind::Stone => &STONE_COLOR,
-            CellKind::Water => &WATER_COLOR,
-        }
-        .choose(&mut rand::rng())
-        .unwrap()
-    }
+/// Compact material id, resolved through the [`Materials`] registry.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct CellKind(pub u8);
 
-    pub fn movement(&self) -> CellMovement {
-        match self {
-            CellKind::Sand => SAND_MOVEMENT,
-            CellKind::WetSand => WET_SAND_MOVEMENT,
-            CellKind::Stone => &[],
-            CellKind::Water => WATER_MOVEMENT,
-        }
-    }
-
-    pub fn transitions(&self) -> &[CellTransition] {
-        match self {
-            CellKind::Sand => SAND_TRANSITIONS,
-            CellKind::WetSand => WET_SAND_TRANSITIONS,
-            CellKind::Stone => STONE_TRANSITIONS,
-            CellKind::Water => WATER_TRANSITIONS,
-        }
-    }
-
-    pub fn is_liquid(&self) -> bool {
-        matches!(self, CellKind::Water)
-    }
+impl CellKind {
+    /// Reserved for the walls of a bounded world, resolves to an indestructible solid.
+    pub const BOUNDARY: CellKind = CellKind(u8::MAX);
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct CellUpdate {
     pub updated:      bool,
     pub new_pos:      Option<GridPos>,
-    pub new_momentum: f32,
+    pub new_velocity: Vec2,
     pub transition:   Option<CellTransition>,
     pub swapped:      bool,
+    /// Touched a reaction partner, whether the reaction happened or not.
+    pub contact:      bool,
+    /// Could have flowed but was held back by its viscosity, so it isn't at rest.
+    pub hesitated:    bool,
+    /// Velocity handed to the cell that was run into.
+    pub impulse:      Option<(GridPos, Vec2)>,
+    /// Cell that was run into the hardest and the relative speed of that hit.
+    pub impact:       Option<(GridPos, f32)>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Cell {
-    pub kind:     CellKind,
-    pub idx:      usize,
-    pub momentum: f32,
-    pub sleeping: bool,
+    pub kind:        CellKind,
+    pub shade:       u8,
+    /// Speed in cells per tick.
+    pub velocity:    Vec2,
+    pub temperature: f32,
+    /// Water soaked up by a porous material, from 0 (dry) to 1 (saturated), or carried by a liquid that wets them.
+    pub moisture:    f32,
+    /// Tick at which a cell of a material with a [`Lifetime`] expires.
+    pub expires:     Option<u64>,
+    /// Tick at which a conductor took charge, cleared once it is ready to take the next one.
+    pub charged:     Option<u64>,
+    /// Consecutive ticks the cell has been touching a reaction partner.
+    pub contact:     u8,
+    pub sleeping:    bool,
+    pub clock:       u8,
 
     sleep_counter: u32,
 }
 
 impl Cell {
-    pub fn new(kind: CellKind, idx: usize) -> Self {
-        Self { kind, idx, momentum: 0.0, sleeping: false, sleep_counter: 0 }
+    pub const fn new(kind: CellKind, shade: u8) -> Self {
+        Self {
+            kind,
+            shade,
+            velocity: Vec2::ZERO,
+            temperature: AMBIENT_TEMPERATURE,
+            moisture: 0.0,
+            expires: None,
+            charged: None,
+            contact: 0,
+            sleeping: false,
+            clock: 0,
+            sleep_counter: 0,
+        }
     }
 
     pub fn wake(&mut self) {
@@ -177,65 +201,202 @@ impl Cell {
         }
     }
 
-    pub fn update<'a, L>(&self, pos: GridPos, lookup: L, acceleration: f32) -> CellUpdate
+    /// Moves the cell for a tick under the local `acceleration`. The material's movement rules are turned to follow
+    /// its direction, without any the cell only keeps drifting along its velocity.
+    pub fn update<'a, L>(
+        &self,
+        pos: GridPos,
+        lookup: L,
+        materials: &Materials,
+        acceleration: Vec2,
+        rng: &mut SimRng,
+    ) -> CellUpdate
     where
         L: Fn(GridPos) -> Option<&'a Cell>,
     {
+        let material = &materials[self.kind];
+        if material.movement.is_empty() {
+            return CellUpdate::default(); // Static materials never move
+        }
+        let orientation = Orientation::new(acceleration);
+        let (down, below) = (acceleration.normalize_or_zero(), orientation.down());
+        let across = |velocity: Vec2| velocity - down * velocity.dot(down);
+        let mut velocity = if material.gas {
+            across(self.velocity) * (1.0 - material.friction) - down * GAS_TRAVEL
+        } else {
+            material.accelerate(self.velocity, acceleration)
+        };
+        let movement: &[MovementOptionGroup] = if acceleration == Vec2::ZERO { &[] } else { &material.movement };
+
         let mut update = CellUpdate {
             updated:      false,
             new_pos:      None,
-            new_momentum: self.momentum + acceleration,
+            new_velocity: velocity,
             transition:   None,
             swapped:      false,
+            contact:      false,
+            hesitated:    false,
+            impulse:      None,
+            impact:       None,
+        };
+        let hesitant = material.viscosity > 0.0 && rng.random::<f32>() < material.viscosity;
+        let cohesive = material.cohesive(self.moisture);
+        let slump = material.saturated(self.moisture).then(|| MovementOptionGroup(vec![(1, 0), (-1, 0)]));
+
+        // Reactions and displacement end the update, returns `true` if one of them happened
+        let react = |tmp_pos: GridPos, offset: GridPos, collider: &Cell, rng: &mut SimRng, update: &mut CellUpdate| {
+            let new_pos = (tmp_pos.0 + offset.0, tmp_pos.1 + offset.1);
+            if let Some(transition) = material
+                .transitions
+                .iter()
+                .find(|t| t.condition == TransitionCondition::Contact(collider.kind) && t.catalysed(pos, &lookup))
+                .copied()
+            {
+                if tmp_pos != pos {
+                    return true; // Stop in front of it, the reaction happens from there next tick
+                }
+                update.contact = true;
+                if self.contact >= transition.duration && rng.random::<f32>() < transition.chance {
+                    update.updated = true;
+                    update.new_pos = Some(new_pos);
+                    update.transition = Some(transition);
+                    update.new_velocity = Vec2::ZERO;
+                    return true;
+                }
+            } else if material.displaces(&materials[collider.kind], orientation.unrotate(offset)) {
+                if tmp_pos != pos {
+                    return true; // Stop in front of it, the swap happens from there next tick
+                }
+                update.updated = true;
+                update.new_pos = Some(new_pos);
+                update.swapped = true;
+                update.new_velocity = Vec2::ZERO;
+                return true;
+            }
+            false
         };
-
-        let transitions = self.kind.transitions();
 
         let mut tmp_pos = pos;
-        let mut momentum = update.new_momentum;
-        let mut last_dir = (0, 0);
-
-        while momentum > 0.0 {
-            let mut dead_end = true;
-            for group in self.kind.movement() {
-                let mut shuffled = group.shuffled();
-                shuffled.insert(0, last_dir); // Try to follow the last direction first
+        let mut trace = Trace::default();
+        // Always try at least one step, leaving room to flow sideways
+        let mut steps = velocity.abs().max_element().clamp(1.0, MAX_TRAVEL - material.dispersion as f32);
+        let (mut spread, mut heading) = (0, None);
+        'steps: while steps >= 1.0 {
+            steps -= 1.0;
+
+            // Follow the velocity first
+            let primary = trace.next(velocity);
+            let mut impact = None;
+            if let Some(offset) = primary {
+                let new_pos = (tmp_pos.0 + offset.0, tmp_pos.1 + offset.1);
+                let Some(collider) = lookup(new_pos) else {
+                    update.updated = true;
+                    update.new_pos = Some(new_pos);
+                    tmp_pos = new_pos;
+                    continue;
+                };
+                if react(tmp_pos, offset, collider, rng, &mut update) {
+                    return update;
+                }
+
+                let relative = velocity - collider.velocity;
+                if update.impact.is_none_or(|(_, speed)| relative.length() > speed) {
+                    update.impact = Some((new_pos, relative.length()));
+                }
+                if !materials[collider.kind].solid && relative.length() > IMPULSE_THRESHOLD {
+                    update.impulse = Some((new_pos, relative * IMPULSE_TRANSFER));
+                }
+                // A diagonal step is blocked on the axes whose direct neighbour is occupied, or on both at a corner
+                let mut blocked = BVec2::new(
+                    offset.0 != 0 && (offset.1 == 0 || lookup((tmp_pos.0 + offset.0, tmp_pos.1)).is_some()),
+                    offset.1 != 0 && (offset.0 == 0 || lookup((tmp_pos.0, tmp_pos.1 + offset.1)).is_some()),
+                );
+                if !blocked.any() {
+                    blocked = BVec2::new(offset.0 != 0, offset.1 != 0);
+                }
+
+                impact = Some(relative.length());
+                velocity = collider.velocity + material.bounce(relative, blocked);
+                update.new_velocity = velocity;
+                trace = Trace::default();
+                if collider.velocity.dot(velocity) > 0.0 {
+                    break; // Following a cell that moves the same way, wait behind it
+                }
+            }
+
+            // Then the material's own movement rules, preferring to keep flowing the same way or along the velocity
+            for group in movement.iter().chain(&slump) {
+                let mut shuffled: Vec<_> = group.shuffled(rng).into_iter().map(|o| orientation.rotate(o)).collect();
+                shuffled.sort_by_key(|&o| (Some(o) != heading, Vec2::new(o.0 as f32, o.1 as f32).dot(velocity) <= 0.0));
                 for offset in shuffled {
+                    if Some(offset) == primary {
+                        continue; // Already tried
+                    }
+                    let sideways = orientation.unrotate(offset).0 != 0;
                     let new_pos = (tmp_pos.0 + offset.0, tmp_pos.1 + offset.1);
                     let Some(collider) = lookup(new_pos) else {
+                        if cohesive && sideways && lookup((new_pos.0 + below.0, new_pos.1 + below.1)).is_some() {
+                            continue; // Damp cells only give way to steeper drops
+                        }
+                        if hesitant {
+                            update.hesitated = true;
+                            break 'steps; // Too viscous to flow this tick
+                        }
                         update.updated = true;
                         update.new_pos = Some(new_pos);
                         tmp_pos = new_pos;
-
-                        dead_end = false;
-                        last_dir = offset;
-                        momentum -= 1.0; // Decrease momentum TODO: do this better lul
-                        break;
+                        heading = Some(offset);
+
+                        if let Some(impact) = impact {
+                            velocity = material.slide(impact, offset);
+                            update.new_velocity = velocity;
+                        }
+                        if orientation.unrotate(offset).1 == 0 {
+                            spread += 1;
+                            if spread < material.dispersion {
+                                steps = steps.max(1.0); // Keeps flowing sideways up to the dispersion
+                            }
+                        }
+                        continue 'steps;
                     };
-                    if let Some(transition) = transitions.iter().find(|t| t.condition == collider.kind).cloned() {
-                        update.updated = true;
-                        update.new_pos = Some(new_pos);
-                        update.transition = Some(transition);
-                        update.new_momentum = 0.0;
-                        return update;
-                    } else if collider.kind.is_liquid() && !self.kind.is_liquid() {
-                        update.updated = true;
-                        update.new_pos = Some(new_pos);
-                        update.swapped = true;
-                        update.new_momentum = 0.0;
+                    if react(tmp_pos, offset, collider, rng, &mut update) {
                         return update;
                     }
                 }
-                if !dead_end {
-                    break;
-                }
-            }
-            if dead_end {
-                update.new_momentum = 0.0;
-                break;
             }
+            break; // Dead end
+        }
+
+        // Anything resting on the ground loses speed to friction
+        if !material.gas && lookup((tmp_pos.0 + below.0, tmp_pos.1 + below.1)).is_some() {
+            let sliding = across(update.new_velocity);
+            let slowed = sliding * (1.0 - material.friction);
+            let slowed = if slowed.length() < REST_SPEED { Vec2::ZERO } else { slowed };
+            update.new_velocity += slowed - sliding;
         }
 
         update
     }
 }
+
+/// Walks the grid along a straight line in single steps to the direct neighbours.
+#[derive(Debug, Clone, Copy, Default)]
+struct Trace {
+    exact:  Vec2,
+    walked: GridPos,
+}
+
+impl Trace {
+    /// The next step along `velocity`, `None` if the cell is standing still.
+    fn next(&mut self, velocity: Vec2) -> Option<GridPos> {
+        let longest = velocity.abs().max_element();
+        if longest < f32::EPSILON {
+            return None;
+        }
+        self.exact += velocity / longest;
+        let target = (self.exact.x.round() as isize, self.exact.y.round() as isize);
+        let step = (target.0 - self.walked.0, target.1 - self.walked.1);
+        self.walked = target;
+        Some(step)
+    }
+}
@@ -0,0 +1,275 @@
+use hashbrown::HashMap;
+
+use crate::sandbox::{cell::Cell, sandbox::GridPos};
+
+pub const CHUNK_SIZE: isize = 64;
+const CHUNK_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+pub type ChunkPos = (isize, isize);
+
+/// Area of a chunk in local cell positions, both corners included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DirtyRect {
+    pub min: GridPos,
+    pub max: GridPos,
+}
+
+impl DirtyRect {
+    pub const FULL: DirtyRect = DirtyRect { min: (0, 0), max: (CHUNK_SIZE - 1, CHUNK_SIZE - 1) };
+
+    pub fn point(pos: GridPos) -> Self {
+        Self { min: pos, max: pos }
+    }
+
+    pub fn union(self, other: DirtyRect) -> Self {
+        Self {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+
+    pub fn contains(&self, pos: GridPos) -> bool {
+        (self.min.0..=self.max.0).contains(&pos.0) && (self.min.1..=self.max.1).contains(&pos.1)
+    }
+
+    /// The rect grown by `margin` cells on every side, clamped to the chunk.
+    pub fn grown(self, margin: isize) -> Self {
+        Self {
+            min: ((self.min.0 - margin).max(0), (self.min.1 - margin).max(0)),
+            max: ((self.max.0 + margin).min(CHUNK_SIZE - 1), (self.max.1 + margin).min(CHUNK_SIZE - 1)),
+        }
+    }
+}
+
+/// A dense `CHUNK_SIZE` x `CHUNK_SIZE` block of cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    cells: Box<[Option<Cell>]>,
+    count: usize,
+    dirty: Option<DirtyRect>,
+
+    next_expiry: Option<u64>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self {
+            cells:       vec![None; CHUNK_AREA].into_boxed_slice(),
+            count:       0,
+            dirty:       None,
+            next_expiry: None,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Whether the chunk may contain awake cells and has to be visited on the next tick.
+    pub fn is_awake(&self) -> bool {
+        self.dirty.is_some()
+    }
+
+    /// The area that changed or holds awake cells, the only part visited on the next tick.
+    pub fn dirty(&self) -> Option<DirtyRect> {
+        self.dirty
+    }
+
+    /// Hands over the dirty area for visiting it, the chunk falls asleep unless something marks it again.
+    pub fn take_dirty(&mut self) -> Option<DirtyRect> {
+        self.dirty.take()
+    }
+
+    /// Grows the dirty area to include the cell at `idx`.
+    pub fn mark(&mut self, idx: usize) {
+        self.mark_rect(DirtyRect::point(Self::local_pos(idx)));
+    }
+
+    pub fn mark_rect(&mut self, rect: DirtyRect) {
+        self.dirty = Some(self.dirty.map_or(rect, |dirty| dirty.union(rect)));
+    }
+
+    /// Whether the chunk has to be visited at `tick` because it is awake or one of its cells expires.
+    pub fn is_due(&self, tick: u64) -> bool {
+        self.is_awake() || self.expiring(tick)
+    }
+
+    /// The area the resting pass visits when the chunk is updated at `tick`: the dirty area grown by a cell, or the
+    /// whole chunk while cells may expire.
+    pub fn resting_area(&self, tick: u64) -> Option<DirtyRect> {
+        if self.expiring(tick) { Some(DirtyRect::FULL) } else { self.dirty.map(|dirty| dirty.grown(1)) }
+    }
+
+    /// Whether one of the cells may expire at `tick`. Expiring cells aren't tracked by position, so the whole chunk
+    /// is visited then.
+    pub fn expiring(&self, tick: u64) -> bool {
+        self.next_expiry.is_some_and(|expiry| expiry <= tick)
+    }
+
+    /// Forgets the earliest expiry, so it can be collected again while visiting every cell.
+    pub fn clear_expiry(&mut self) {
+        self.next_expiry = None;
+    }
+
+    /// Lowers the earliest expiry of the chunk to `expires` if needed.
+    pub fn track_expiry(&mut self, expires: Option<u64>) {
+        if let Some(expires) = expires {
+            self.next_expiry = Some(self.next_expiry.map_or(expires, |next| next.min(expires)));
+        }
+    }
+
+    pub fn wake(&mut self, idx: usize) -> bool {
+        let Some(cell) = self.cells[idx].as_mut() else {
+            return false;
+        };
+        cell.wake();
+        self.mark(idx);
+        true
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&Cell> {
+        self.cells[idx].as_ref()
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut Cell> {
+        self.cells[idx].as_mut()
+    }
+
+    pub fn insert(&mut self, idx: usize, cell: Cell) -> Option<Cell> {
+        if !cell.sleeping {
+            self.mark(idx);
+        }
+        self.track_expiry(cell.expires);
+        let previous = self.cells[idx].replace(cell);
+        if previous.is_none() {
+            self.count += 1;
+        }
+        previous
+    }
+
+    pub fn remove(&mut self, idx: usize) -> Option<Cell> {
+        let previous = self.cells[idx].take();
+        if previous.is_some() {
+            self.count -= 1;
+        }
+        previous
+    }
+
+    /// Iterates over the occupied cells of this chunk together with their local index.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Cell)> {
+        self.cells.iter().enumerate().filter_map(|(idx, cell)| cell.as_ref().map(|c| (idx, c)))
+    }
+
+    pub fn local_pos(idx: usize) -> GridPos {
+        ((idx as isize) % CHUNK_SIZE, (idx as isize) / CHUNK_SIZE)
+    }
+
+    pub fn local_index(pos: GridPos) -> usize {
+        (pos.1.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + pos.0.rem_euclid(CHUNK_SIZE)) as usize
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Unbounded cell storage split into chunks that are allocated on demand and dropped once empty.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChunkGrid {
+    chunks: HashMap<ChunkPos, Chunk>,
+}
+
+impl ChunkGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn chunk_pos(pos: GridPos) -> ChunkPos {
+        (pos.0.div_euclid(CHUNK_SIZE), pos.1.div_euclid(CHUNK_SIZE))
+    }
+
+    pub fn chunk_origin(chunk_pos: ChunkPos) -> GridPos {
+        (chunk_pos.0 * CHUNK_SIZE, chunk_pos.1 * CHUNK_SIZE)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(Chunk::count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn get(&self, pos: &GridPos) -> Option<&Cell> {
+        self.chunks.get(&Self::chunk_pos(*pos))?.get(Chunk::local_index(*pos))
+    }
+
+    pub fn get_mut(&mut self, pos: &GridPos) -> Option<&mut Cell> {
+        self.chunks.get_mut(&Self::chunk_pos(*pos))?.get_mut(Chunk::local_index(*pos))
+    }
+
+    pub fn contains(&self, pos: &GridPos) -> bool {
+        self.get(pos).is_some()
+    }
+
+    pub fn insert(&mut self, pos: GridPos, cell: Cell) -> Option<Cell> {
+        self.chunks.entry(Self::chunk_pos(pos)).or_default().insert(Chunk::local_index(pos), cell)
+    }
+
+    pub fn remove(&mut self, pos: &GridPos) -> Option<Cell> {
+        let chunk_pos = Self::chunk_pos(*pos);
+        let chunk = self.chunks.get_mut(&chunk_pos)?;
+        let cell = chunk.remove(Chunk::local_index(*pos))?;
+        if chunk.is_empty() {
+            self.chunks.remove(&chunk_pos);
+        }
+        Some(cell)
+    }
+
+    pub fn wake(&mut self, pos: &GridPos) -> bool {
+        self.chunks.get_mut(&Self::chunk_pos(*pos)).is_some_and(|chunk| chunk.wake(Chunk::local_index(*pos)))
+    }
+
+    /// Detaches a chunk from the grid so it can be worked on independently, see [`ChunkGrid::put_chunk`].
+    pub fn take_chunk(&mut self, chunk_pos: &ChunkPos) -> Option<Chunk> {
+        self.chunks.remove(chunk_pos)
+    }
+
+    pub fn put_chunk(&mut self, chunk_pos: ChunkPos, chunk: Chunk) {
+        if !chunk.is_empty() {
+            self.chunks.insert(chunk_pos, chunk);
+        }
+    }
+
+    /// Chunks that have to be updated at `tick`, see [`Chunk::is_due`].
+    pub fn due_chunks(&self, tick: u64) -> Vec<ChunkPos> {
+        let mut due: Vec<ChunkPos> =
+            self.chunks.iter().filter(|(_, chunk)| chunk.is_due(tick)).map(|(chunk_pos, _)| *chunk_pos).collect();
+        due.sort_unstable();
+        due
+    }
+
+    pub fn chunk(&self, chunk_pos: &ChunkPos) -> Option<&Chunk> {
+        self.chunks.get(chunk_pos)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (&ChunkPos, &Chunk)> {
+        self.chunks.iter()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (GridPos, &Cell)> {
+        self.chunks.iter().flat_map(|(chunk_pos, chunk)| {
+            let origin = Self::chunk_origin(*chunk_pos);
+            chunk.iter().map(move |(idx, cell)| {
+                let local = Chunk::local_pos(idx);
+                ((origin.0 + local.0, origin.1 + local.1), cell)
+            })
+        })
+    }
+}
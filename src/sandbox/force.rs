@@ -0,0 +1,122 @@
+use glam::Vec2;
+
+use crate::sandbox::sandbox::GridPos;
+
+/// The direct neighbour offsets counter-clockwise, starting straight down.
+const RING: [GridPos; 8] = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+
+/// Area a [`ForceField`] acts on, in grid units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldShape {
+    Rect { min: Vec2, max: Vec2 },
+    Circle { center: Vec2, radius: f32 },
+}
+
+impl FieldShape {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        match *self {
+            FieldShape::Rect { min, max } => pos.cmpge(min).all() && pos.cmple(max).all(),
+            FieldShape::Circle { center, radius } => pos.distance_squared(center) <= radius * radius,
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        match *self {
+            FieldShape::Rect { min, max } => (min + max) / 2.0,
+            FieldShape::Circle { center, .. } => center,
+        }
+    }
+}
+
+/// What a [`ForceField`] does to the cells inside it, in cells per second squared like gravity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldEffect {
+    /// Pushes on top of gravity, e.g. wind.
+    Push(Vec2),
+    /// Replaces gravity, e.g. anti-gravity pulling upwards.
+    Gravity(Vec2),
+    /// Pulls towards the center of the field, negative strengths push away from it.
+    Attract(f32),
+}
+
+/// A placeable zone that bends the movement of the cells inside it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForceField {
+    pub shape:  FieldShape,
+    pub effect: FieldEffect,
+}
+
+impl ForceField {
+    pub fn new(shape: FieldShape, effect: FieldEffect) -> Self {
+        Self { shape, effect }
+    }
+}
+
+/// Gravity and the force fields placed in the world.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forces {
+    pub gravity: Vec2,
+    pub fields:  Vec<ForceField>,
+}
+
+impl Forces {
+    /// Acceleration at `pos`. Fields apply in the order they were placed, a later gravity field overrides an earlier
+    /// one.
+    pub fn at(&self, pos: GridPos) -> Vec2 {
+        let pos = Vec2::new(pos.0 as f32, pos.1 as f32);
+        let (mut gravity, mut push) = (self.gravity, Vec2::ZERO);
+        for field in self.fields.iter().filter(|field| field.shape.contains(pos)) {
+            match field.effect {
+                FieldEffect::Push(force) => push += force,
+                FieldEffect::Gravity(force) => gravity = force,
+                FieldEffect::Attract(strength) => push += (field.shape.center() - pos).normalize_or_zero() * strength,
+            }
+        }
+        gravity + push
+    }
+}
+
+/// Maps the movement offsets of materials, written for gravity pulling along -Y, onto the local direction of the
+/// acceleration in steps of 45 degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Orientation(usize);
+
+impl Orientation {
+    /// Orientation closest to the direction of `acceleration`, without any it stays upright.
+    pub fn new(acceleration: Vec2) -> Self {
+        if acceleration == Vec2::ZERO {
+            return Self::default();
+        }
+        let angle = acceleration.x.atan2(-acceleration.y).to_degrees();
+        Self(((angle / 45.0).round() as isize).rem_euclid(8) as usize)
+    }
+
+    /// The neighbour offset pointing down.
+    pub fn down(&self) -> GridPos {
+        RING[self.0]
+    }
+
+    /// Turns an offset relative to down into a grid offset.
+    pub fn rotate(&self, offset: GridPos) -> GridPos {
+        Self::turn(offset, self.0)
+    }
+
+    /// Turns a grid offset into one relative to down.
+    pub fn unrotate(&self, offset: GridPos) -> GridPos {
+        Self::turn(offset, RING.len() - self.0)
+    }
+
+    /// How far down `pos` lies, higher values are lower.
+    pub fn depth(&self, pos: GridPos) -> isize {
+        let down = self.down();
+        pos.0 * down.0 + pos.1 * down.1
+    }
+
+    // ----------------< Private >----------------
+    fn turn(offset: GridPos, steps: usize) -> GridPos {
+        match RING.iter().position(|&o| o == offset) {
+            Some(idx) => RING[(idx + steps) % RING.len()],
+            None => offset,
+        }
+    }
+}
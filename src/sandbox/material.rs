@@ -0,0 +1,742 @@
+use std::{fmt, ops::Index, path::Path};
+
+use glam::{BVec2, Vec2};
+use hashbrown::HashMap;
+use log::debug;
+use serde::Deserialize;
+
+use crate::{
+    sandbox::{
+        cell::{
+            Cell, CellKind, CellTransition, Explosion, Ignition, Lifetime, MovementOptionGroup, TransitionCondition,
+            count_neighbours,
+        },
+        sandbox::GridPos,
+        store::WAKE_RADIUS,
+    },
+    utils::Color,
+};
+
+const DEFAULT_MATERIALS: &str = include_str!("../../assets/materials.toml");
+
+/// Temperature of the surrounding air and the default temperature of newly placed cells.
+pub const AMBIENT_TEMPERATURE: f32 = 20.0;
+
+const CONDUCTION_RATE: f32 = 0.2; // Share of the difference to the equilibrium exchanged per neighbour and tick
+const MIN_BOUNCE: f32 = 1.0; // Slower rebounds come to rest instead
+const MAX_DISPERSION: u8 = 16;
+const AIR_COOLING_RATE: f32 = 0.005; // Share of the difference to the ambient temperature lost per exposed side
+const DIFFUSION_RATE: f32 = 0.1; // Share of the moisture difference exchanged between porous neighbours per tick
+const ABSORB_RATE: f32 = 0.2; // Moisture a porous cell soaks up from a touching liquid per tick
+const EVAPORATION_RATE: f32 = 0.0005; // Moisture lost per exposed side and tick at ambient temperature
+const DAMPNESS: f32 = 0.2; // Moisture above which porous materials hold steeper slopes
+const SATURATION: f32 = 0.8; // Moisture above which porous materials lose their hold and slump
+const WET_DARKENING: f32 = 0.35; // Share of the colour lost when saturated
+const CHARGE_GLOW: f32 = 0.5; // How far charged conductors are lightened towards white
+pub const MAX_BLAST_RADIUS: f32 = 32.0; // Bounds the area a single blast scans and wakes
+
+#[derive(Debug)]
+pub enum MaterialError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterialError::Io(e) => write!(f, "failed to read material file: {e}"),
+            MaterialError::Parse(e) => write!(f, "failed to parse material file: {e}"),
+            MaterialError::Invalid(msg) => write!(f, "invalid material definition: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
+impl From<std::io::Error> for MaterialError {
+    fn from(e: std::io::Error) -> Self {
+        MaterialError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for MaterialError {
+    fn from(e: toml::de::Error) -> Self {
+        MaterialError::Parse(e)
+    }
+}
+
+/// Runtime description of a single material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialDef {
+    pub name:          String,
+    pub palette:       Vec<Color>,
+    pub movement:      Vec<MovementOptionGroup>,
+    pub transitions:   Vec<CellTransition>,
+    pub liquid:        bool,
+    /// Gases ignore gravity, rise and drift sideways.
+    pub gas:           bool,
+    pub solid:         bool,
+    pub density:       f32,
+    /// Share of the speed along a surface lost when hitting or sliding over it.
+    pub friction:      f32,
+    /// Share of the speed into a surface bouncing back off it.
+    pub restitution:   f32,
+    /// Fastest speed in cells per tick gravity accelerates the material to.
+    pub max_fall:      f32,
+    /// Cells the material may flow sideways per tick.
+    pub dispersion:    u8,
+    /// Chance per tick that the material doesn't flow although it could.
+    pub viscosity:     f32,
+    pub conductivity:  f32,
+    pub heat_capacity: f32,
+    pub temperature:   f32,
+    /// Porous materials soak up and pass on moisture.
+    pub porous:        bool,
+    /// Moisture of newly placed cells. Liquids that carry some wet the porous materials they touch and get used up.
+    pub moisture:      f32,
+    /// Conductors take charge from live neighbours and pass it on a tick later.
+    pub conductive:    bool,
+    /// Ticks a conductor can't take charge again after passing it on.
+    pub refractory:    u8,
+    /// Batteries are always live.
+    pub battery:       bool,
+    /// Blast strength the material withstands, weaker blasts only push loose cells away.
+    pub resistance:    f32,
+    /// What the material turns into when a blast is too strong for it, `None` destroys it.
+    pub debris:        Option<CellKind>,
+    pub explosion:     Option<Explosion>,
+    pub ignition:      Option<Ignition>,
+    /// Burning materials set flammable neighbours alight.
+    pub burning:       bool,
+    pub lifetime:      Option<Lifetime>,
+}
+
+impl MaterialDef {
+    /// Whether a cell of this material moving by `offset` pushes `other` out of the way: heavier materials sink
+    /// through lighter liquids and gases and lighter ones rise through heavier ones.
+    pub fn displaces(&self, other: &MaterialDef, offset: GridPos) -> bool {
+        if self.solid || other.solid || !(other.liquid || other.gas) {
+            return false;
+        }
+        match offset.1.signum() {
+            -1 => self.density > other.density,
+            1 => self.density < other.density,
+            _ => false,
+        }
+    }
+
+    /// Velocity after `acceleration` pulled on a falling cell for a tick, capped at the terminal velocity along it.
+    /// Cells that are already faster, e.g. thrown ones, keep their speed.
+    pub fn accelerate(&self, velocity: Vec2, acceleration: Vec2) -> Vec2 {
+        let down = acceleration.normalize_or_zero();
+        let falling = velocity.dot(down);
+        if down == Vec2::ZERO || falling >= self.max_fall {
+            return velocity;
+        }
+        velocity + down * ((falling + acceleration.length()).min(self.max_fall) - falling)
+    }
+
+    /// Velocity after running into something on the `blocked` axes: the blocked part bounces back if that is fast
+    /// enough to matter, the rest is slowed down by friction.
+    pub fn bounce(&self, velocity: Vec2, blocked: BVec2) -> Vec2 {
+        let rebound = |v: f32| {
+            let v = -v * self.restitution;
+            if v.abs() < MIN_BOUNCE { 0.0 } else { v }
+        };
+        let slowed = |v: f32| v * (1.0 - self.friction);
+
+        Vec2::new(
+            if blocked.x { rebound(velocity.x) } else { slowed(velocity.x) },
+            if blocked.y { rebound(velocity.y) } else { slowed(velocity.y) },
+        )
+    }
+
+    /// Velocity of a cell that hit something at `speed` and got deflected into `offset`, e.g. down a slope.
+    pub fn slide(&self, speed: f32, offset: GridPos) -> Vec2 {
+        Vec2::new(offset.0 as f32, offset.1 as f32).normalize_or_zero() * speed * (1.0 - self.friction)
+    }
+
+    /// The first transition that holds for the cell at `pos` without it having to move: temperature, moisture,
+    /// charge and neighbourhood conditions, each with their catalyst if they need one. `powered` tells whether the
+    /// cell is live or touches a live cell.
+    pub fn resting_transition<'a, L>(
+        &self,
+        pos: GridPos,
+        cell: &Cell,
+        powered: bool,
+        lookup: L,
+    ) -> Option<&CellTransition>
+    where
+        L: Fn(GridPos) -> Option<&'a Cell>,
+    {
+        let (temperature, moisture) = (cell.temperature, cell.moisture);
+        self.transitions.iter().find(|t| {
+            let holds = match t.condition {
+                TransitionCondition::Above(limit) => temperature > limit,
+                TransitionCondition::Below(limit) => temperature < limit,
+                TransitionCondition::Near { kind, count, radius } => {
+                    count_neighbours(pos, kind, radius, &lookup) >= count as usize
+                }
+                TransitionCondition::Moist(limit) => moisture > limit,
+                TransitionCondition::Charged => powered,
+                TransitionCondition::Contact(_) => false,
+            };
+            holds && t.catalysed(pos, &lookup)
+        })
+    }
+
+    /// Moves two touching cells towards their common equilibrium temperature. The heat exchanged is conserved.
+    pub fn conduct(&self, temperature: f32, other: &MaterialDef, other_temperature: f32) -> (f32, f32) {
+        let rate = (self.conductivity * other.conductivity).sqrt() * CONDUCTION_RATE;
+        let equilibrium = (self.heat_capacity * temperature + other.heat_capacity * other_temperature)
+            / (self.heat_capacity + other.heat_capacity);
+
+        (temperature + (equilibrium - temperature) * rate, other_temperature + (equilibrium - other_temperature) * rate)
+    }
+
+    /// Moves a cell with `exposed` empty sides towards the ambient temperature.
+    pub fn cool(&self, temperature: f32, exposed: u32) -> f32 {
+        let rate = (AIR_COOLING_RATE * exposed as f32 * self.conductivity / self.heat_capacity).min(1.0);
+        temperature + (AMBIENT_TEMPERATURE - temperature) * rate
+    }
+
+    /// Passes moisture between two touching cells: porous neighbours even out, a porous cell soaks up what a
+    /// touching liquid carries. The moisture exchanged is conserved.
+    pub fn soak(&self, moisture: f32, other: &MaterialDef, other_moisture: f32) -> (f32, f32) {
+        let flow = match (self.porous, other.porous) {
+            (true, true) => (other_moisture - moisture) * DIFFUSION_RATE,
+            (true, false) if other.liquid => ABSORB_RATE.min(other_moisture).min(1.0 - moisture).max(0.0),
+            (false, true) if self.liquid => -ABSORB_RATE.min(moisture).min(1.0 - other_moisture).max(0.0),
+            _ => 0.0,
+        };
+        (moisture + flow, other_moisture - flow)
+    }
+
+    /// Moisture a porous cell with `exposed` empty sides keeps after drying for a tick, faster the hotter it is.
+    pub fn evaporate(&self, moisture: f32, temperature: f32, exposed: u32) -> f32 {
+        if !self.porous {
+            return moisture;
+        }
+        let heat = (temperature / AMBIENT_TEMPERATURE).max(1.0);
+        (moisture - EVAPORATION_RATE * exposed as f32 * heat).max(0.0)
+    }
+
+    /// Whether the cell is damp enough to stick together and hold slopes steeper than dry cells do.
+    pub fn cohesive(&self, moisture: f32) -> bool {
+        self.porous && moisture >= DAMPNESS && !self.saturated(moisture)
+    }
+
+    /// Whether the cell passes charge on to touching conductors during `tick`: batteries always, conductors the tick
+    /// after they took charge.
+    pub fn live(&self, cell: &Cell, tick: u64) -> bool {
+        self.battery || self.conductive && cell.charged.is_some_and(|charged| charged + 1 == tick)
+    }
+
+    /// Whether a conductor that took charge at `charged` is ready to take charge again during `tick`.
+    pub fn recovered(&self, charged: u64, tick: u64) -> bool {
+        tick > charged + 1 + self.refractory as u64
+    }
+
+    /// Whether the cell is soaked enough to slump sideways like a thick liquid.
+    pub fn saturated(&self, moisture: f32) -> bool {
+        self.porous && moisture >= SATURATION
+    }
+}
+
+/// Registry of all materials, indexed by [`CellKind`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Materials {
+    defs:     Vec<MaterialDef>,
+    ids:      HashMap<String, CellKind>,
+    boundary: MaterialDef,
+}
+
+impl Materials {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MaterialError> {
+        debug!("Loading materials from {}", path.as_ref().display());
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, MaterialError> {
+        let file: MaterialFile = toml::from_str(source)?;
+        if file.materials.is_empty() || file.materials.len() > CellKind::BOUNDARY.0 as usize {
+            return Err(MaterialError::Invalid(format!("expected 1 to 255 materials, found {}", file.materials.len())));
+        }
+
+        let mut ids = HashMap::new();
+        for (idx, raw) in file.materials.iter().enumerate() {
+            if ids.insert(raw.name.clone(), CellKind(idx as u8)).is_some() {
+                return Err(MaterialError::Invalid(format!("duplicate material `{}`", raw.name)));
+            }
+        }
+
+        let lookup = |owner: &str, name: &str| {
+            ids.get(name)
+                .copied()
+                .ok_or_else(|| MaterialError::Invalid(format!("`{owner}` references unknown material `{name}`")))
+        };
+
+        let mut defs = Vec::with_capacity(file.materials.len());
+        for raw in file.materials {
+            if raw.palette.is_empty() || raw.palette.len() > u8::MAX as usize {
+                return Err(MaterialError::Invalid(format!("`{}` needs 1 to 255 palette colours", raw.name)));
+            }
+            if let Some(offset) = raw.movement.iter().flatten().find(|o| o.0.abs() > 1 || o.1.abs() > 1) {
+                return Err(MaterialError::Invalid(format!(
+                    "`{}` has movement step {offset:?}, steps must stay within the direct neighbours",
+                    raw.name
+                )));
+            }
+            if raw.liquid && raw.gas || raw.solid && (raw.liquid || raw.gas) {
+                return Err(MaterialError::Invalid(format!(
+                    "`{}` can only be one of `solid`, `liquid` or `gas`",
+                    raw.name
+                )));
+            }
+            if !(0.0..=1.0).contains(&raw.friction) || !(0.0..=1.0).contains(&raw.restitution) {
+                return Err(MaterialError::Invalid(format!(
+                    "`{}` needs friction and restitution between 0 and 1",
+                    raw.name
+                )));
+            }
+            if !(1..=MAX_DISPERSION).contains(&raw.dispersion) || !(0.0..1.0).contains(&raw.viscosity) {
+                return Err(MaterialError::Invalid(format!(
+                    "`{}` needs a dispersion between 1 and {MAX_DISPERSION} and a viscosity of at least 0 and below 1",
+                    raw.name
+                )));
+            }
+            if raw.max_fall <= 0.0 {
+                return Err(MaterialError::Invalid(format!("`{}` needs a positive terminal velocity", raw.name)));
+            }
+            if raw.resistance < 0.0 {
+                return Err(MaterialError::Invalid(format!("`{}` needs a non-negative blast resistance", raw.name)));
+            }
+            if let Some(explosion) = &raw.explosion
+                && (!(1.0..=MAX_BLAST_RADIUS).contains(&explosion.radius)
+                    || !positive_finite(explosion.force)
+                    || explosion.crush.is_some_and(|crush| !positive_finite(crush)))
+            {
+                return Err(MaterialError::Invalid(format!(
+                    "`{}` needs an explosion radius between 1 and {MAX_BLAST_RADIUS}, a positive finite force and a \
+                     positive finite crush speed",
+                    raw.name
+                )));
+            }
+            if raw.conductive && raw.refractory == 0 {
+                return Err(MaterialError::Invalid(format!("`{}` needs a refractory period of at least 1", raw.name)));
+            }
+            if !(0.0..=1.0).contains(&raw.moisture) {
+                return Err(MaterialError::Invalid(format!("`{}` needs a moisture between 0 and 1", raw.name)));
+            }
+            if !(0.0..=1.0).contains(&raw.conductivity) || raw.heat_capacity <= 0.0 {
+                return Err(MaterialError::Invalid(format!(
+                    "`{}` needs a conductivity between 0 and 1 and a positive heat capacity",
+                    raw.name
+                )));
+            }
+
+            if let Some(ignition) = &raw.ignition
+                && !(0.0..=1.0).contains(&ignition.chance)
+            {
+                return Err(MaterialError::Invalid(format!("`{}` needs an ignition chance between 0 and 1", raw.name)));
+            }
+            if raw.ignition.as_ref().is_some_and(|ignition| ignition.into.is_none()) && raw.explosion.is_none() {
+                return Err(MaterialError::Invalid(format!(
+                    "`{}` needs an ignition `into` unless it has an `explosion`",
+                    raw.name
+                )));
+            }
+            let lifetime_range = raw.lifetime.map(|lifetime| match lifetime {
+                RawLifetime::Fixed(ticks) => (ticks, ticks),
+                RawLifetime::Range([min, max]) => (min, max),
+            });
+            if let Some((min, max)) = lifetime_range
+                && min > max
+            {
+                return Err(MaterialError::Invalid(format!("`{}` has an empty lifetime range", raw.name)));
+            }
+            if raw.expires_into.is_some() && raw.lifetime.is_none() {
+                return Err(MaterialError::Invalid(format!("`{}` expires without a lifetime", raw.name)));
+            }
+
+            let ignition = match &raw.ignition {
+                Some(ignition) => {
+                    let into = ignition.into.as_deref().map(|into| lookup(&raw.name, into)).transpose()?;
+                    Some(Ignition { chance: ignition.chance, into })
+                }
+                None => None,
+            };
+            let lifetime = match lifetime_range {
+                Some(ticks) => Some(Lifetime {
+                    ticks,
+                    into: raw.expires_into.as_deref().map(|into| lookup(&raw.name, into)).transpose()?,
+                }),
+                None => None,
+            };
+
+            let transitions = raw
+                .transitions
+                .iter()
+                .map(|t| {
+                    let condition = match (&t.with, t.above, t.below, &t.near, t.moist, t.charged) {
+                        (Some(with), None, None, None, None, false) => {
+                            TransitionCondition::Contact(lookup(&raw.name, with)?)
+                        }
+                        (None, Some(above), None, None, None, false) => TransitionCondition::Above(above),
+                        (None, None, Some(below), None, None, false) => TransitionCondition::Below(below),
+                        (None, None, None, None, Some(moist), false) => TransitionCondition::Moist(moist),
+                        (None, None, None, None, None, true) => TransitionCondition::Charged,
+                        (None, None, None, Some(near), None, false) => TransitionCondition::Near {
+                            kind:   lookup(&raw.name, near)?,
+                            count:  t.count.unwrap_or(1),
+                            radius: t.radius.unwrap_or(1),
+                        },
+                        _ => {
+                            return Err(MaterialError::Invalid(format!(
+                                "`{}` has a transition that needs exactly one of `with`, `above`, `below`, `near`, \
+                                 `moist` or `charged`",
+                                raw.name
+                            )));
+                        }
+                    };
+                    if t.near.is_none() && (t.count.is_some() || t.radius.is_some()) {
+                        return Err(MaterialError::Invalid(format!(
+                            "`{}` has a transition with `count` or `radius` but without `near`",
+                            raw.name
+                        )));
+                    }
+                    if t.radius.is_some_and(|radius| radius == 0 || radius as isize > WAKE_RADIUS) {
+                        return Err(MaterialError::Invalid(format!(
+                            "`{}` has a transition radius outside of 1 to {WAKE_RADIUS}",
+                            raw.name
+                        )));
+                    }
+                    if !condition.is_contact() && (t.other.is_some() || t.duration > 0) {
+                        return Err(MaterialError::Invalid(format!(
+                            "`{}` has a transition with `other` or `duration` that doesn't need contact",
+                            raw.name
+                        )));
+                    }
+                    if !(0.0..=1.0).contains(&t.chance) {
+                        return Err(MaterialError::Invalid(format!(
+                            "`{}` has a transition chance outside of 0 to 1",
+                            raw.name
+                        )));
+                    }
+                    if t.explode && raw.explosion.is_none() {
+                        return Err(MaterialError::Invalid(format!(
+                            "`{}` has a transition that explodes without an `explosion`",
+                            raw.name
+                        )));
+                    }
+                    if t.into.is_none() && t.other.is_none() && !t.remove && t.byproduct.is_none() && !t.explode {
+                        return Err(MaterialError::Invalid(format!("`{}` has a transition without effect", raw.name)));
+                    }
+
+                    let kind = |name: &Option<String>| name.as_deref().map(|name| lookup(&raw.name, name)).transpose();
+                    Ok(CellTransition {
+                        condition,
+                        into: kind(&t.into)?,
+                        other: kind(&t.other)?,
+                        remove: t.remove,
+                        byproduct: kind(&t.byproduct)?,
+                        catalyst: kind(&t.catalyst)?,
+                        chance: t.chance,
+                        duration: t.duration,
+                        explode: t.explode,
+                    })
+                })
+                .collect::<Result<_, MaterialError>>()?;
+
+            defs.push(MaterialDef {
+                palette: raw.palette.iter().map(|c| Color::new(c[0], c[1], c[2], 1.0)).collect(),
+                movement: raw.movement.into_iter().map(MovementOptionGroup).collect(),
+                transitions,
+                liquid: raw.liquid,
+                gas: raw.gas,
+                solid: raw.solid,
+                density: raw.density,
+                friction: raw.friction,
+                restitution: raw.restitution,
+                max_fall: raw.max_fall,
+                dispersion: raw.dispersion,
+                viscosity: raw.viscosity,
+                conductivity: raw.conductivity,
+                heat_capacity: raw.heat_capacity,
+                temperature: raw.temperature,
+                porous: raw.porous,
+                moisture: raw.moisture,
+                conductive: raw.conductive,
+                refractory: raw.refractory,
+                battery: raw.battery,
+                resistance: raw.resistance,
+                debris: raw.debris.as_deref().map(|debris| lookup(&raw.name, debris)).transpose()?,
+                explosion: raw.explosion.map(|explosion| Explosion {
+                    radius: explosion.radius,
+                    force:  explosion.force,
+                    crush:  explosion.crush,
+                }),
+                ignition,
+                burning: raw.burning,
+                lifetime,
+                name: raw.name,
+            });
+        }
+
+        Ok(Self { defs, ids, boundary: Self::boundary() })
+    }
+
+    pub fn id(&self, name: &str) -> Option<CellKind> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.defs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+
+    pub fn kinds(&self) -> impl Iterator<Item = CellKind> {
+        (0..self.defs.len()).map(|idx| CellKind(idx as u8))
+    }
+
+    /// The colour of the cell's shade, darkened the wetter a porous cell is and lightened while a conductor is
+    /// charged.
+    pub fn color(&self, cell: &Cell) -> Color {
+        let material = &self[cell.kind];
+        let color = material.palette[cell.shade as usize % material.palette.len()];
+        if material.conductive && cell.charged.is_some() {
+            color.lightened(CHARGE_GLOW)
+        } else if material.porous {
+            color.darkened(cell.moisture * WET_DARKENING)
+        } else {
+            color
+        }
+    }
+
+    // ----------------< Private >----------------
+    /// The material behind [`CellKind::BOUNDARY`]: static, solid and indestructible, without any heat exchange.
+    fn boundary() -> MaterialDef {
+        MaterialDef {
+            name:          "boundary".to_string(),
+            palette:       vec![Color::BLACK],
+            movement:      Vec::new(),
+            transitions:   Vec::new(),
+            liquid:        false,
+            gas:           false,
+            solid:         true,
+            density:       f32::INFINITY,
+            friction:      default_friction(),
+            restitution:   0.0,
+            max_fall:      default_terminal_velocity(),
+            dispersion:    default_dispersion(),
+            viscosity:     0.0,
+            conductivity:  0.0,
+            heat_capacity: 1.0,
+            temperature:   AMBIENT_TEMPERATURE,
+            porous:        false,
+            moisture:      0.0,
+            conductive:    false,
+            refractory:    default_refractory(),
+            battery:       false,
+            resistance:    f32::INFINITY,
+            debris:        None,
+            explosion:     None,
+            ignition:      None,
+            burning:       false,
+            lifetime:      None,
+        }
+    }
+}
+
+impl Default for Materials {
+    fn default() -> Self {
+        Self::parse(DEFAULT_MATERIALS).expect("Built-in material definitions are invalid")
+    }
+}
+
+impl Index<CellKind> for Materials {
+    type Output = MaterialDef;
+
+    fn index(&self, kind: CellKind) -> &Self::Output {
+        match kind {
+            CellKind::BOUNDARY => &self.boundary,
+            kind => &self.defs[kind.0 as usize],
+        }
+    }
+}
+
+// ----------------< File format >----------------
+#[derive(Debug, Deserialize)]
+struct MaterialFile {
+    #[serde(rename = "material", default)]
+    materials: Vec<RawMaterial>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMaterial {
+    name:          String,
+    palette:       Vec<[f32; 3]>,
+    #[serde(default)]
+    movement:      Vec<Vec<GridPos>>,
+    #[serde(default)]
+    transitions:   Vec<RawTransition>,
+    #[serde(default)]
+    liquid:        bool,
+    #[serde(default)]
+    gas:           bool,
+    #[serde(default)]
+    solid:         bool,
+    #[serde(default = "default_one")]
+    density:       f32,
+    #[serde(default = "default_friction")]
+    friction:      f32,
+    #[serde(default)]
+    restitution:   f32,
+    #[serde(rename = "terminal_velocity", default = "default_terminal_velocity")]
+    max_fall:      f32,
+    #[serde(default = "default_dispersion")]
+    dispersion:    u8,
+    #[serde(default)]
+    viscosity:     f32,
+    #[serde(default = "default_conductivity")]
+    conductivity:  f32,
+    #[serde(default = "default_one")]
+    heat_capacity: f32,
+    #[serde(default = "default_temperature")]
+    temperature:   f32,
+    #[serde(default)]
+    porous:        bool,
+    #[serde(default)]
+    moisture:      f32,
+    #[serde(default)]
+    conductive:    bool,
+    #[serde(default = "default_refractory")]
+    refractory:    u8,
+    #[serde(default)]
+    battery:       bool,
+    #[serde(rename = "blast_resistance", default = "default_one")]
+    resistance:    f32,
+    #[serde(rename = "blast_into")]
+    debris:        Option<String>,
+    explosion:     Option<RawExplosion>,
+    ignition:      Option<RawIgnition>,
+    #[serde(default)]
+    burning:       bool,
+    lifetime:      Option<RawLifetime>,
+    expires_into:  Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+enum RawLifetime {
+    Fixed(u16),
+    Range([u16; 2]),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawIgnition {
+    chance: f32,
+    into:   Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawExplosion {
+    radius: f32,
+    force:  f32,
+    crush:  Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTransition {
+    with:      Option<String>,
+    above:     Option<f32>,
+    below:     Option<f32>,
+    near:      Option<String>,
+    moist:     Option<f32>,
+    #[serde(default)]
+    charged:   bool,
+    count:     Option<u8>,
+    radius:    Option<u8>,
+    catalyst:  Option<String>,
+    into:      Option<String>,
+    other:     Option<String>,
+    #[serde(default)]
+    remove:    bool,
+    byproduct: Option<String>,
+    #[serde(default = "default_one")]
+    chance:    f32,
+    #[serde(default)]
+    duration:  u8,
+    #[serde(default)]
+    explode:   bool,
+}
+
+fn positive_finite(value: f32) -> bool {
+    value > 0.0 && value.is_finite()
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_friction() -> f32 {
+    0.5
+}
+
+fn default_terminal_velocity() -> f32 {
+    20.0
+}
+
+fn default_dispersion() -> u8 {
+    1
+}
+
+fn default_refractory() -> u8 {
+    4
+}
+
+fn default_conductivity() -> f32 {
+    0.5
+}
+
+fn default_temperature() -> f32 {
+    AMBIENT_TEMPERATURE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaterialError, Materials};
+
+    fn explosive(explosion: &str) -> Result<Materials, MaterialError> {
+        Materials::parse(&format!(
+            r#"
+            [[material]]
+            name = "charge"
+            palette = [[0.8, 0.2, 0.2]]
+            solid = true
+            explosion = {explosion}
+            "#
+        ))
+    }
+
+    #[test]
+    fn explosions_need_finite_force_and_crush() {
+        assert!(explosive("{ radius = 4.0, force = 10.0, crush = 5.0 }").is_ok());
+        for explosion in [
+            "{ radius = 4.0, force = inf }",
+            "{ radius = 4.0, force = nan }",
+            "{ radius = 4.0, force = 10.0, crush = inf }",
+            "{ radius = 4.0, force = 10.0, crush = nan }",
+        ] {
+            assert!(matches!(explosive(explosion), Err(MaterialError::Invalid(_))), "{explosion} parsed");
+        }
+    }
+}
@@ -1,7 +1,20 @@
+mod bounds;
 mod brush;
 mod cell;
+mod chunk;
+mod force;
+mod material;
+mod particle;
+mod pressure;
+mod region;
+mod rng;
+#[allow(clippy::module_inception)]
 mod sandbox;
+mod store;
 
+pub use bounds::{Bounds, Edge};
 pub use brush::Brush;
-pub use cell::CellKind;
-pub use sandbox::Sandbox;
+pub use cell::{Cell, CellKind};
+pub use force::{FieldEffect, FieldShape, ForceField};
+pub use material::Materials;
+pub use sandbox::{CellChange, GridPos, RngState, Sandbox};
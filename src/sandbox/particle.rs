@@ -0,0 +1,72 @@
+use glam::{BVec2, Vec2};
+
+use crate::sandbox::{cell::Cell, material::Materials, region::MAX_TRAVEL, sandbox::GridPos};
+
+/// A cell that left the grid because it moved too fast, flying freely until it lands on something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    /// Exact position in grid units, the cell's velocity keeps driving it.
+    pub pos:  Vec2,
+    pub cell: Cell,
+}
+
+impl Particle {
+    pub fn new(pos: GridPos, cell: Cell) -> Self {
+        Self { pos: Vec2::new(pos.0 as f32, pos.1 as f32), cell }
+    }
+
+    /// The grid position the particle is currently over.
+    pub fn grid_pos(&self) -> GridPos {
+        Self::to_grid(self.pos)
+    }
+
+    /// Flies one tick along the velocity. Returns the spot to settle in once the particle runs into an occupied cell,
+    /// or, for gases, once it got slow enough for the grid to move it again. Particles pass through the cells they
+    /// start out inside, but never through `walled` positions, so the returned spot may be taken.
+    pub fn update<F, W>(&mut self, occupied: F, walled: W, materials: &Materials, acceleration: Vec2) -> Option<GridPos>
+    where
+        F: Fn(GridPos) -> bool,
+        W: Fn(GridPos) -> bool,
+    {
+        let material = &materials[self.cell.kind];
+        let velocity = &mut self.cell.velocity;
+        if material.gas {
+            *velocity *= 1.0 - material.friction;
+        } else {
+            *velocity = material.accelerate(*velocity, acceleration);
+        }
+
+        let steps = velocity.abs().max_element().ceil().max(1.0);
+        let step = *velocity / steps;
+        for _ in 0..steps as usize {
+            let here = Self::to_grid(self.pos);
+            let next = Self::to_grid(self.pos + step);
+            if next != here && occupied(next) {
+                if occupied(here) {
+                    if walled(next) {
+                        return Some(here); // Buried against a wall, settles as close to here as it can
+                    }
+                    self.pos += step; // Still inside whatever it passed through, keep flying
+                    continue;
+                }
+                let offset = (next.0 - here.0, next.1 - here.1);
+                let blocked = BVec2::new(
+                    offset.0 != 0 && (offset.1 == 0 || occupied((next.0, here.1))),
+                    offset.1 != 0 && (offset.0 == 0 || occupied((here.0, next.1))),
+                );
+                let blocked = if blocked.any() { blocked } else { BVec2::new(offset.0 != 0, offset.1 != 0) };
+                *velocity = material.bounce(*velocity, blocked).clamp_length_max(MAX_TRAVEL);
+                return Some(here);
+            }
+            self.pos += step;
+        }
+
+        let here = Self::to_grid(self.pos);
+        (material.gas && velocity.length() <= MAX_TRAVEL && !occupied(here)).then_some(here)
+    }
+
+    // ----------------< Private >----------------
+    fn to_grid(pos: Vec2) -> GridPos {
+        (pos.x.round() as isize, pos.y.round() as isize)
+    }
+}
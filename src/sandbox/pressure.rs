@@ -0,0 +1,47 @@
+use std::collections::VecDeque;
+
+use hashbrown::HashSet;
+
+use crate::sandbox::{
+    cell::{Cell, CellKind},
+    force::Orientation,
+    region::MAX_TRAVEL,
+    sandbox::GridPos,
+};
+
+const SEARCH_LIMIT: usize = 512; // Most cells of a liquid body visited while looking for an outlet
+
+/// Where the pressure of its liquid body pushes the cell at `pos`: the lowest free spot next to the connected cells
+/// of the same kind, as long as it lies below `pos` in the local `orientation`. Only surface cells are pushed, so
+/// connected vessels level out and liquid rises through pipes up to the level of its source.
+pub fn outlet<'a, L>(pos: GridPos, kind: CellKind, orientation: Orientation, lookup: L) -> Option<GridPos>
+where
+    L: Fn(GridPos) -> Option<&'a Cell>,
+{
+    let down = orientation.down();
+    if lookup((pos.0 - down.0, pos.1 - down.1)).is_some() {
+        return None; // Held down by whatever is on top
+    }
+
+    let mut visited: HashSet<GridPos> = HashSet::from_iter([pos]);
+    let mut queue = VecDeque::from([pos]);
+    let depth = |pos| orientation.depth(pos);
+    let mut outlet: Option<GridPos> = None;
+    while let Some(body) = queue.pop_front() {
+        for (dx, dy) in [(0, -1), (1, 0), (-1, 0), (0, 1)] {
+            let next = (body.0 + dx, body.1 + dy);
+            let reachable = (next.0 - pos.0).abs() as f32 <= MAX_TRAVEL && (next.1 - pos.1).abs() as f32 <= MAX_TRAVEL;
+            if !reachable || !visited.insert(next) {
+                continue;
+            }
+            match lookup(next) {
+                None if depth(next) > depth(pos) && outlet.is_none_or(|outlet| depth(next) > depth(outlet)) => {
+                    outlet = Some(next)
+                }
+                Some(cell) if cell.kind == kind && visited.len() < SEARCH_LIMIT => queue.push_back(next),
+                _ => {}
+            }
+        }
+    }
+    outlet
+}
@@ -0,0 +1,627 @@
+use glam::Vec2;
+use hashbrown::HashMap;
+use rand::Rng;
+
+use crate::sandbox::{
+    bounds::{Edges, WALL},
+    cell::{Cell, CellTransition, Explosion},
+    chunk::{CHUNK_SIZE, Chunk, ChunkGrid, ChunkPos, DirtyRect},
+    force::{Forces, Orientation},
+    material::Materials,
+    particle::Particle,
+    pressure,
+    rng::SimRng,
+    sandbox::{CellChange, GridPos},
+    store::{CellStore, WAKE_RADIUS},
+};
+
+/// Number of update phases per tick. Chunks are assigned to phases by their position modulo 3 on both axes, so
+/// chunks sharing a phase are three chunks apart and the 3x3 regions around them never overlap.
+pub const PHASES: usize = 9;
+
+/// Furthest a cell may travel in a single tick. Keeps movers and the neighbours they wake inside their region.
+pub const MAX_TRAVEL: f32 = (CHUNK_SIZE - WAKE_RADIUS) as f32;
+
+const HEAT_EPSILON: f32 = 0.01; // Temperature changes below this let a cell fall asleep
+const MOISTURE_EPSILON: f32 = 0.0001; // Moisture changes below this let a cell fall asleep, less moisture dries up
+const WETNESS_SHADES: f32 = 10.0; // Moisture steps that are told apart when recolouring wet cells
+
+/// A chunk together with its eight neighbours, detached from the grid so it can be updated on its own thread.
+#[derive(Debug)]
+pub struct Region<'a> {
+    center:     ChunkPos,
+    chunks:     [Option<Chunk>; 9],
+    resting:    [Option<DirtyRect>; 9],
+    materials:  &'a Materials,
+    rng:        SimRng,
+    tick:       u64,
+    changes:    Option<Vec<CellChange>>,
+    particles:  Vec<Particle>,
+    explosions: Vec<(GridPos, Explosion)>,
+}
+
+impl<'a> Region<'a> {
+    pub fn phase(chunk_pos: ChunkPos) -> usize {
+        (chunk_pos.0.rem_euclid(3) + chunk_pos.1.rem_euclid(3) * 3) as usize
+    }
+
+    /// Detaches the chunks around `center` from the grid. `resting` holds the resting area of every chunk updated
+    /// during this tick, as it was when the tick started.
+    pub fn extract(
+        grid: &mut ChunkGrid,
+        center: ChunkPos,
+        resting: &HashMap<ChunkPos, DirtyRect>,
+        materials: &'a Materials,
+        seed: u64,
+        tick: u64,
+        track_changes: bool,
+    ) -> Self {
+        let chunks = std::array::from_fn(|slot| grid.take_chunk(&Self::slot_chunk_pos(center, slot)));
+        let resting = std::array::from_fn(|slot| resting.get(&Self::slot_chunk_pos(center, slot)).copied());
+        // Mixing in the position gives every region its own stream no matter which thread runs it
+        let seed = seed ^ (center.0 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (center.1 as u64).rotate_left(32);
+
+        Self {
+            center,
+            chunks,
+            resting,
+            materials,
+            rng: SimRng::new(seed),
+            tick,
+            changes: track_changes.then(Vec::new),
+            particles: Vec::new(),
+            explosions: Vec::new(),
+        }
+    }
+
+    /// Puts the chunks back into the grid and returns the changes recorded while updating, together with the cells
+    /// that left the grid as particles and the explosions that went off. Blasts can reach beyond the region, so the
+    /// sandbox sets them off once every region is back.
+    pub fn restore(self, grid: &mut ChunkGrid) -> (Vec<CellChange>, Vec<Particle>, Vec<(GridPos, Explosion)>) {
+        for (slot, chunk) in self.chunks.into_iter().enumerate() {
+            if let Some(chunk) = chunk {
+                grid.put_chunk(Self::slot_chunk_pos(self.center, slot), chunk);
+            }
+        }
+        (self.changes.unwrap_or_default(), self.particles, self.explosions)
+    }
+
+    /// Exchanges heat and moisture through the dirty area of the center chunk and runs its resting transitions, then
+    /// updates every awake cell in it, bottom row first. Cells woken above the rows already visited still get their
+    /// turn, the area grows along with them. Whatever is left dirty at the chunk border marks the neighbouring chunk
+    /// across it, so changes spread over chunk borders.
+    pub fn update(&mut self, forces: &Forces, dt: f32, edges: &Edges, kill_plane: Option<isize>) {
+        let Some(center) = &mut self.chunks[4] else {
+            return;
+        };
+        let (expiring, dirty) = (center.expiring(self.tick), center.take_dirty());
+        let Some(mut area) = (if expiring { Some(DirtyRect::FULL) } else { dirty }) else {
+            return;
+        };
+        self.update_resting(area, expiring);
+
+        let origin = ChunkGrid::chunk_origin(self.center);
+        let reverse = self.tick % 2 == 1; // Alternate the row direction to avoid a sideways bias
+        let mut y = area.min.1;
+        while y <= area.max.1 {
+            for i in 0..=area.max.0 - area.min.0 {
+                let x = if reverse { area.max.0 - i } else { area.min.0 + i };
+                let pos = (origin.0 + x, origin.1 + y);
+                self.update_cell(pos, forces.at(pos) * dt, edges, kill_plane);
+            }
+            if let Some(dirty) = self.chunks[4].as_ref().and_then(Chunk::dirty) {
+                area = area.union(dirty);
+            }
+            y += 1;
+        }
+
+        if let Some(dirty) = self.chunks[4].as_ref().and_then(Chunk::dirty) {
+            self.mark_borders(dirty);
+        }
+    }
+
+    // ----------------< Private >----------------
+    /// Marks `pos` dirty, so it is visited again next tick.
+    fn keep_awake_at(&mut self, pos: GridPos) {
+        if let Some(chunk) = Self::slot(self.center, pos).and_then(|slot| self.chunks[slot].as_mut()) {
+            chunk.mark(Chunk::local_index(pos));
+        }
+    }
+
+    /// Marks the cells of the neighbouring chunks that touch the `dirty` area of the center chunk across its border.
+    fn mark_borders(&mut self, dirty: DirtyRect) {
+        // The span next to `min..=max` on one axis across the side at `side`, or `None` if it doesn't reach that side
+        let across = |side: isize, min: isize, max: isize| match side {
+            -1 => (min == 0).then_some((CHUNK_SIZE - 1, CHUNK_SIZE - 1)),
+            1 => (max == CHUNK_SIZE - 1).then_some((0, 0)),
+            _ => Some((min, max)),
+        };
+        for slot in (0..9).filter(|&slot| slot != 4) {
+            let (dx, dy) = (slot as isize % 3 - 1, slot as isize / 3 - 1);
+            let (Some(xs), Some(ys)) = (across(dx, dirty.min.0, dirty.max.0), across(dy, dirty.min.1, dirty.max.1))
+            else {
+                continue;
+            };
+            if let Some(chunk) = &mut self.chunks[slot] {
+                chunk.mark_rect(DirtyRect { min: (xs.0, ys.0), max: (xs.1, ys.1) });
+            }
+        }
+    }
+
+    /// Expires cells whose lifetime is up, exchanges heat and moisture between every cell around the `area` of the
+    /// center chunk and its neighbours, sleeping or not, and applies the transitions that don't need movement. Each
+    /// touching pair is handled once per tick: pairs inside the center chunk always, pairs across a border by the
+    /// chunk whose resting area covered its side of the pair when the tick started, the lower left one if both or
+    /// neither did. The area is grown by a cell so the pairs along its left and bottom side are handled as well.
+    /// `expiring` chunks are visited whole and collect their earliest expiry again.
+    fn update_resting(&mut self, area: DirtyRect, expiring: bool) {
+        let materials = self.materials;
+        let origin = ChunkGrid::chunk_origin(self.center);
+        if expiring && let Some(center) = &mut self.chunks[4] {
+            center.clear_expiry(); // Collected again below
+        }
+
+        let area = area.grown(1);
+        for y in area.min.1..=area.max.1 {
+            for x in area.min.0..=area.max.0 {
+                let pos = (origin.0 + x, origin.1 + y);
+                if self.get(pos).and_then(|cell| cell.expires).is_some_and(|expires| expires <= self.tick) {
+                    self.expire(pos);
+                }
+                let Some(&cell) = self.get(pos) else {
+                    continue;
+                };
+                if let Some(center) = &mut self.chunks[4] {
+                    center.track_expiry(cell.expires);
+                }
+                let material = &materials[cell.kind];
+                let mut temperature = cell.temperature;
+                let mut moisture = cell.moisture;
+                let mut exposed = 0;
+
+                for (dx, dy) in [(1, 0), (0, 1), (-1, 0), (0, -1)] {
+                    let neighbour_pos = (pos.0 + dx, pos.1 + dy);
+                    let Some(&neighbour) = self.get(neighbour_pos) else {
+                        exposed += 1;
+                        continue;
+                    };
+                    let leaves_center = !(0..CHUNK_SIZE).contains(&(x + dx)) || !(0..CHUNK_SIZE).contains(&(y + dy));
+                    let owned = if !leaves_center {
+                        dx + dy > 0
+                    } else if dx + dy > 0 {
+                        self.rests(pos) || !self.rests(neighbour_pos)
+                    } else {
+                        !self.rests(neighbour_pos) && (self.rests(pos) || !self.scheduled(neighbour_pos))
+                    };
+                    if !owned {
+                        continue; // The other side of this pair handles it
+                    }
+
+                    let (new_temperature, neighbour_temperature) =
+                        material.conduct(temperature, &materials[neighbour.kind], neighbour.temperature);
+                    temperature = new_temperature;
+                    if (neighbour_temperature - neighbour.temperature).abs() > HEAT_EPSILON {
+                        self.keep_awake_at(neighbour_pos);
+                    }
+                    if let Some(neighbour) = self.get_mut(neighbour_pos) {
+                        neighbour.temperature = neighbour_temperature;
+                    }
+
+                    let (new_moisture, neighbour_moisture) =
+                        material.soak(moisture, &materials[neighbour.kind], neighbour.moisture);
+                    moisture = new_moisture;
+                    self.set_moisture(neighbour_pos, neighbour_moisture);
+                }
+                temperature = material.cool(temperature, exposed);
+                moisture = material.evaporate(moisture, temperature, exposed);
+
+                if (temperature - cell.temperature).abs() > HEAT_EPSILON {
+                    self.keep_awake_at(pos);
+                }
+                if let Some(cell) = self.get_mut(pos) {
+                    cell.temperature = temperature;
+                }
+                if self.set_moisture(pos, moisture) {
+                    let powered = self.powered(pos, self.tick);
+                    self.charge(pos, powered);
+                    self.react_at_rest(pos, powered);
+                }
+            }
+        }
+    }
+
+    /// Updates the moisture of the cell at `pos`, recolouring it once it looks noticeably wetter or drier and waking
+    /// it once it is soaked enough to slump. A liquid that gave away all it carried is used up. Returns whether the
+    /// cell is still there.
+    fn set_moisture(&mut self, pos: GridPos, moisture: f32) -> bool {
+        let Some(&cell) = self.get(pos) else {
+            return false;
+        };
+        let moisture = if moisture < MOISTURE_EPSILON { 0.0 } else { moisture };
+        let material = &self.materials[cell.kind];
+        if moisture == cell.moisture {
+            return true;
+        }
+        if !material.porous && material.moisture > 0.0 && moisture <= 0.0 {
+            self.remove_cell(pos);
+            return false;
+        }
+
+        let (porous, saturated) = (material.porous, !material.saturated(cell.moisture) && material.saturated(moisture));
+        if (moisture - cell.moisture).abs() > MOISTURE_EPSILON {
+            self.keep_awake_at(pos);
+        }
+        let Some(cell) = self.get_mut(pos) else {
+            return false;
+        };
+        let recolour = porous && (cell.moisture * WETNESS_SHADES).round() != (moisture * WETNESS_SHADES).round();
+        cell.moisture = moisture;
+        let cell = *cell;
+        if recolour {
+            self.record(CellChange::Updated(pos, cell));
+        }
+        if saturated && self.wake(pos) {
+            self.wake_neighbours(pos);
+        }
+        true
+    }
+
+    /// Whether the cell at `pos` or one of its direct neighbours is live at `tick`.
+    fn powered(&self, pos: GridPos, tick: u64) -> bool {
+        [(0, 0), (1, 0), (0, 1), (-1, 0), (0, -1)].into_iter().any(|(dx, dy)| {
+            self.get((pos.0 + dx, pos.1 + dy)).is_some_and(|cell| self.materials[cell.kind].live(cell, tick))
+        })
+    }
+
+    /// Lets a conductor at `pos` recover from its last charge and take a new one if it is `powered`. Charge only
+    /// becomes live the tick after it was taken, so it travels one cell per tick no matter the update order, and
+    /// the chunks it may travel into next are kept awake.
+    fn charge(&mut self, pos: GridPos, powered: bool) {
+        let Some(&cell) = self.get(pos) else {
+            return;
+        };
+        let material = &self.materials[cell.kind];
+        if !material.conductive {
+            return;
+        }
+
+        let mut charged = cell.charged.filter(|&charged| !material.recovered(charged, self.tick));
+        if charged.is_none() && powered {
+            charged = Some(self.tick);
+            for (dx, dy) in [(1, 0), (0, 1), (-1, 0), (0, -1)] {
+                self.keep_awake_at((pos.0 + dx, pos.1 + dy));
+            }
+        }
+        // Waits for charge taken next to it this tick, even if the mark of that neighbour was already used up
+        if charged.is_some() || self.powered(pos, self.tick + 1) {
+            self.keep_awake_at(pos);
+        }
+        if charged != cell.charged
+            && let Some(cell) = self.get_mut(pos)
+        {
+            cell.charged = charged;
+            let cell = *cell;
+            self.record(CellChange::Updated(pos, cell));
+        }
+    }
+
+    /// Applies the first temperature, moisture, charge or neighbourhood transition that holds for the cell at `pos`.
+    /// One that holds but loses its roll keeps the chunk awake for another try next tick.
+    fn react_at_rest(&mut self, pos: GridPos, powered: bool) {
+        let (chunks, center) = (&self.chunks, self.center);
+        let Some(cell) = self.get(pos) else {
+            return;
+        };
+        let Some(transition) = self.materials[cell.kind]
+            .resting_transition(pos, cell, powered, |p| Self::lookup(chunks, center, p))
+            .copied()
+        else {
+            return;
+        };
+
+        if self.rng.random::<f32>() < transition.chance {
+            self.react(pos, None, transition);
+            if transition.into.is_some() && self.wake(pos) {
+                self.wake_neighbours(pos); // Removals and byproducts wake their surroundings themselves
+            }
+        } else {
+            self.keep_awake_at(pos);
+        }
+    }
+
+    /// Applies a transition of the cell at `pos`, `other` being the cell it ran into for contact reactions.
+    fn react(&mut self, pos: GridPos, other: Option<GridPos>, transition: CellTransition) {
+        if let Some(into) = transition.into {
+            self.change_cell_kind(pos, into);
+        }
+        if let (Some(other), Some(other_kind)) = (other, transition.other) {
+            self.change_cell_kind(other, other_kind);
+        }
+        if transition.remove {
+            self.remove_cell(pos);
+        }
+        if let Some(byproduct) = transition.byproduct {
+            self.spawn_near(other.unwrap_or(pos), byproduct);
+        }
+        if transition.explode {
+            self.detonate(pos);
+        }
+    }
+
+    /// Removes an explosive cell and queues its explosion.
+    fn detonate(&mut self, pos: GridPos) {
+        let Some(explosion) = self.get(pos).and_then(|cell| self.materials[cell.kind].explosion) else {
+            return;
+        };
+        self.remove_cell(pos);
+        self.explosions.push((pos, explosion));
+    }
+
+    /// Gives every flammable neighbour of a burning cell the chance to catch fire.
+    fn spread_fire(&mut self, pos: GridPos) {
+        let (materials, clock) = (self.materials, self.clock());
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let neighbour = (pos.0 + dx, pos.1 + dy);
+                let Some(ignition) = self.get(neighbour).and_then(|cell| materials[cell.kind].ignition) else {
+                    continue;
+                };
+                if self.rng.random::<f32>() < ignition.chance {
+                    let Some(into) = ignition.into else {
+                        self.detonate(neighbour);
+                        continue;
+                    };
+                    self.change_cell_kind(neighbour, into);
+                    if let Some(cell) = self.get_mut(neighbour) {
+                        cell.clock = clock; // Fresh fire waits for the next tick before spreading further
+                    }
+                    self.wake(neighbour);
+                }
+            }
+        }
+    }
+
+    /// Replaces or removes a cell whose lifetime is up.
+    fn expire(&mut self, pos: GridPos) {
+        let Some(cell) = self.get(pos) else {
+            return;
+        };
+        match self.materials[cell.kind].lifetime.and_then(|lifetime| lifetime.into) {
+            Some(into) => {
+                self.change_cell_kind(pos, into);
+                if self.wake(pos) {
+                    self.wake_neighbours(pos);
+                }
+            }
+            None => {
+                self.remove_cell(pos);
+            }
+        }
+    }
+
+    /// Moves a gas that could not rise. Drifting doesn't count as activity, so trapped gas still falls asleep and
+    /// doesn't keep waking its neighbours, unless it burns.
+    fn drift(&mut self, from: GridPos, to: GridPos, velocity: Vec2) {
+        let (clock, materials) = (self.clock(), self.materials);
+        self.move_cell(from, to);
+        if let Some(cell) = self.get_mut(to) {
+            cell.clock = clock;
+            cell.velocity = velocity;
+            if !materials[cell.kind].burning {
+                cell.sleep();
+            }
+            if !cell.sleeping {
+                self.keep_awake_at(to);
+            }
+        }
+    }
+
+    /// Moves a liquid cell the pressure of its body pushed out.
+    fn flow(&mut self, from: GridPos, to: GridPos) {
+        let clock = self.clock();
+        self.move_cell(from, to);
+        if let Some(cell) = self.get_mut(to) {
+            cell.clock = clock;
+            cell.velocity = Vec2::ZERO;
+        }
+        self.wake(to);
+        self.wake_neighbours(to);
+        self.wake_neighbours(from);
+    }
+
+    fn update_cell(&mut self, pos: GridPos, acceleration: Vec2, edges: &Edges, kill_plane: Option<isize>) {
+        let Some(&cell) = self.get(pos) else {
+            return;
+        };
+        if kill_plane.is_some_and(|y| pos.1 < y) {
+            self.remove_cell(pos);
+            return;
+        }
+        if cell.sleeping || cell.clock == self.clock() {
+            return;
+        }
+
+        let burning = self.materials[cell.kind].burning;
+        if burning {
+            self.spread_fire(pos);
+        }
+
+        if cell.velocity.length() > MAX_TRAVEL {
+            self.remove_cell(pos);
+            self.particles.push(Particle::new(pos, cell));
+            return; // Too fast for the grid, flies on as a particle
+        }
+
+        let (chunks, center) = (&self.chunks, self.center);
+        let lookup = |p| Self::bounded_lookup(chunks, center, edges, p);
+        let update = cell.update(pos, lookup, self.materials, acceleration, &mut self.rng);
+        let clock = self.clock();
+        if let Some(cell) = self.get_mut(pos) {
+            cell.contact = if update.contact { cell.contact.saturating_add(1) } else { 0 };
+        }
+        if let Some((target, impulse)) = update.impulse
+            && let Some(cell) = self.get_mut(target)
+        {
+            cell.velocity += impulse;
+            self.wake(target);
+        }
+        if let Some((target, speed)) = update.impact {
+            for hit in [target, pos] {
+                let crushed = self.get(hit).and_then(|cell| self.materials[cell.kind].explosion).and_then(|e| e.crush);
+                if crushed.is_some_and(|crush| speed > crush) {
+                    self.detonate(hit);
+                }
+            }
+            if !self.occupied(pos) {
+                return; // Blew up
+            }
+        }
+        // Pressure wins over resting or wandering sideways, not over falling, swapping or reacting
+        let orientation = Orientation::new(acceleration);
+        let pressurised = self.materials[cell.kind].liquid
+            && acceleration != Vec2::ZERO
+            && !update.contact
+            && !update.hesitated
+            && !update.swapped
+            && update.transition.is_none()
+            && update.new_pos.is_none_or(|new_pos| orientation.depth(new_pos) <= orientation.depth(pos));
+        let mut hesitated = update.hesitated;
+        if pressurised
+            && let Some(outlet) = pressure::outlet(pos, cell.kind, orientation, |p| {
+                Self::bounded_lookup(&self.chunks, self.center, edges, p)
+            })
+        {
+            let viscosity = self.materials[cell.kind].viscosity;
+            if viscosity == 0.0 || self.rng.random::<f32>() >= viscosity {
+                self.flow(pos, outlet);
+                return;
+            }
+            hesitated = true;
+        }
+        if !update.updated {
+            if let Some(cell) = self.get_mut(pos) {
+                cell.clock = clock;
+                cell.velocity = update.new_velocity;
+                if !update.contact && !hesitated && !burning {
+                    cell.sleep(); // Cells waiting for a reaction, to flow or to spread fire stay awake
+                }
+                if !cell.sleeping {
+                    self.keep_awake_at(pos);
+                }
+            }
+            return;
+        }
+
+        let drifting = self.materials[cell.kind].gas
+            && !update.swapped
+            && update.transition.is_none()
+            && update.new_pos.is_some_and(|new_pos| orientation.depth(new_pos) >= orientation.depth(pos));
+        if drifting {
+            self.drift(pos, update.new_pos.unwrap(), update.new_velocity);
+            return;
+        }
+
+        if update.swapped {
+            self.swap_cells(pos, update.new_pos.unwrap());
+        } else if let Some(transition) = update.transition {
+            self.react(pos, update.new_pos, transition);
+        } else if let Some(new_pos) = update.new_pos {
+            self.move_cell(pos, new_pos);
+        }
+
+        if let Some(new_pos) = update.new_pos
+            && let Some(cell) = self.get_mut(new_pos)
+        {
+            cell.velocity = update.new_velocity;
+            cell.clock = clock;
+            self.wake(new_pos);
+            self.wake_neighbours(new_pos);
+        }
+
+        if self.wake(pos) {
+            self.wake_neighbours(pos);
+        }
+    }
+
+    fn slot_chunk_pos(center: ChunkPos, slot: usize) -> ChunkPos {
+        (center.0 + (slot % 3) as isize - 1, center.1 + (slot / 3) as isize - 1)
+    }
+
+    /// Whether the chunk holding `pos` is updated during this tick.
+    fn scheduled(&self, pos: GridPos) -> bool {
+        Self::slot(self.center, pos).is_some_and(|slot| self.resting[slot].is_some())
+    }
+
+    /// Whether the resting area of the chunk holding `pos` covered it when the tick started.
+    fn rests(&self, pos: GridPos) -> bool {
+        let local = Chunk::local_pos(Chunk::local_index(pos));
+        Self::slot(self.center, pos).and_then(|slot| self.resting[slot]).is_some_and(|area| area.contains(local))
+    }
+
+    fn slot(center: ChunkPos, pos: GridPos) -> Option<usize> {
+        let chunk_pos = ChunkGrid::chunk_pos(pos);
+        let (dx, dy) = (chunk_pos.0 - center.0 + 1, chunk_pos.1 - center.1 + 1);
+        ((0..3).contains(&dx) && (0..3).contains(&dy)).then_some((dy * 3 + dx) as usize)
+    }
+
+    fn lookup(chunks: &[Option<Chunk>; 9], center: ChunkPos, pos: GridPos) -> Option<&Cell> {
+        chunks[Self::slot(center, pos)?].as_ref()?.get(Chunk::local_index(pos))
+    }
+
+    /// Like [`Region::lookup`], but the blocked spots along the `edges` are taken by the [`WALL`].
+    fn bounded_lookup<'b>(
+        chunks: &'b [Option<Chunk>; 9],
+        center: ChunkPos,
+        edges: &Edges,
+        pos: GridPos,
+    ) -> Option<&'b Cell> {
+        if edges.blocked(pos) {
+            return Some(&WALL);
+        }
+        Self::lookup(chunks, center, pos)
+    }
+}
+
+impl CellStore for Region<'_> {
+    fn get(&self, pos: GridPos) -> Option<&Cell> {
+        Self::lookup(&self.chunks, self.center, pos)
+    }
+
+    fn get_mut(&mut self, pos: GridPos) -> Option<&mut Cell> {
+        self.chunks[Self::slot(self.center, pos)?].as_mut()?.get_mut(Chunk::local_index(pos))
+    }
+
+    fn insert(&mut self, pos: GridPos, cell: Cell) -> Option<Cell> {
+        let slot = Self::slot(self.center, pos).expect("Position outside of region");
+        self.chunks[slot].get_or_insert_with(Chunk::new).insert(Chunk::local_index(pos), cell)
+    }
+
+    fn remove(&mut self, pos: GridPos) -> Option<Cell> {
+        self.chunks[Self::slot(self.center, pos)?].as_mut()?.remove(Chunk::local_index(pos))
+    }
+
+    fn wake(&mut self, pos: GridPos) -> bool {
+        let Some(slot) = Self::slot(self.center, pos) else {
+            return false;
+        };
+        self.chunks[slot].as_mut().is_some_and(|chunk| chunk.wake(Chunk::local_index(pos)))
+    }
+
+    fn rng(&mut self) -> &mut SimRng {
+        &mut self.rng
+    }
+
+    fn materials(&self) -> &Materials {
+        self.materials
+    }
+
+    fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    fn record(&mut self, change: CellChange) {
+        if let Some(changes) = &mut self.changes {
+            changes.push(change);
+        }
+    }
+}
@@ -1,223 +1,836 @@
-use glam::Vec3;
+use std::collections::VecDeque;
+
+use glam::Vec2;
 use hashbrown::HashMap;
+use rand::RngCore;
+use rayon::prelude::*;
 
-use crate::{
-    graphics::{Instance, InstanceData, Transform},
-    sandbox::cell::{Cell, CellKind, TransitionTarget},
+use crate::sandbox::{
+    bounds::{Bounds, Edges, Place},
+    cell::{Cell, CellKind},
+    chunk::{CHUNK_SIZE, Chunk, ChunkGrid, ChunkPos, DirtyRect},
+    force::{ForceField, Forces},
+    material::{MAX_BLAST_RADIUS, Materials},
+    particle::Particle,
+    region::{PHASES, Region},
+    rng::SimRng,
+    store::{CellStore, WAKE_RADIUS},
 };
 
-pub const GRID_SIZE: f32 = 32.0;
-const UPDATE_RATE: f64 = 1.0 / 24.0; // 60 FPS
-const GRAVITY: f32 = 5.0; // Gravity effect on cell movement
-const MOMENTUM_THRESHOLD: f32 = 75.0; // Threshold for momentum to be considered irrelevant
+const DEFAULT_TICK_RATE: f64 = 24.0; // Ticks per second
+const DEFAULT_MAX_TICKS_PER_UPDATE: u32 = 8; // Catch-up limit per frame before time is dropped
+const WRAP_SEARCH: isize = 8; // Furthest a cell wrapping or landing onto a taken spot is moved aside
+const DEFAULT_GRAVITY: Vec2 = Vec2::new(0.0, -5.0); // Gravity effect on cell movement
+const THROW_SPEED: f32 = 3.0; // Blast push above which loose cells fly off as particles, gases always stay
 
 pub type GridPos = (isize, isize);
 
+/// A change to the grid, recorded for consumers that mirror the world (e.g. the renderer).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellChange {
+    Inserted(GridPos, Cell),
+    Removed(GridPos),
+    Moved(GridPos, GridPos),
+    Updated(GridPos, Cell),
+}
+
+/// Outcome of a [`Sandbox::update`] call.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct TickReport {
+    /// Number of ticks that were run.
+    pub ticks:    u32,
+    /// Number of owed ticks that were dropped because the catch-up limit was reached.
+    pub skipped:  u32,
+    /// Time in seconds carried over to the next update, less than one tick.
+    pub leftover: f64,
+    /// `leftover` as a fraction of a tick, for interpolating between the last two ticks when rendering.
+    pub alpha:    f64,
+}
+
+/// Where the random draws of a run stand, saved with [`Sandbox::rng_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RngState {
+    pub rng:  u64,
+    /// Number of ticks run so far, every tick derives the RNGs of its regions from it.
+    pub tick: u64,
+}
+
+/// The headless simulation: grid, cells and rules, without any graphics state.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Sandbox {
-    grid:         HashMap<GridPos, Cell>,
-    active_cells: Vec<GridPos>,
+    grid:       ChunkGrid,
+    particles:  Vec<Particle>,
+    materials:  Materials,
+    tick:       u64,
+    parallel:   bool,
+    kill_plane: Option<isize>,
+    bounds:     Option<Bounds>,
+    forces:     Forces,
 
-    mesh_instance: Instance,
+    changes: Option<Vec<CellChange>>,
+
+    seed: u64,
+    rng:  SimRng,
+
+    tick_duration:        f64,
+    max_ticks_per_update: u32,
 
     time_since_last_update: f64,
 }
 
 impl Sandbox {
-    pub fn new(mesh_instance: Instance) -> Self {
-        Self { grid: HashMap::new(), active_cells: Vec::new(), mesh_instance, time_since_last_update: 0.0 }
+    pub fn new() -> Self {
+        Self::with_seed(rand::random())
+    }
+
+    /// Creates a sandbox whose cell movement and colour variation are drawn from an RNG seeded with `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            grid: ChunkGrid::new(),
+            particles: Vec::new(),
+            materials: Materials::default(),
+            tick: 0,
+            parallel: true,
+            kill_plane: None,
+            bounds: None,
+            forces: Forces { gravity: DEFAULT_GRAVITY, fields: Vec::new() },
+            changes: None,
+            seed,
+            rng: SimRng::new(seed),
+            tick_duration: 1.0 / DEFAULT_TICK_RATE,
+            max_ticks_per_update: DEFAULT_MAX_TICKS_PER_UPDATE,
+            time_since_last_update: 0.0,
+        }
+    }
+
+    /// Replaces the built-in materials. Cells refer to materials by id, so this has to happen before any are inserted.
+    pub fn with_materials(mut self, materials: Materials) -> Self {
+        assert!(self.grid.is_empty(), "Materials can't be swapped out while cells exist.");
+        self.materials = materials;
+        self
+    }
+
+    pub fn materials(&self) -> &Materials {
+        &self.materials
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rng_state(&self) -> RngState {
+        RngState { rng: self.rng.state(), tick: self.tick }
+    }
+
+    /// Continues from a saved [`RngState`]. Together with the same cells and inputs the run replays exactly.
+    pub fn restore_rng_state(&mut self, state: RngState) {
+        self.rng.set_state(state.rng);
+        self.tick = state.tick;
+    }
+
+    pub fn tick_rate(&self) -> f64 {
+        1.0 / self.tick_duration
+    }
+
+    /// Sets the number of simulation ticks per second.
+    pub fn set_tick_rate(&mut self, ticks_per_second: f64) {
+        assert!(ticks_per_second > 0.0 && ticks_per_second.is_finite(), "Tick rate must be positive and finite.");
+        self.tick_duration = 1.0 / ticks_per_second;
+    }
+
+    pub fn max_ticks_per_update(&self) -> u32 {
+        self.max_ticks_per_update
+    }
+
+    /// Limits how many owed ticks a single [`Sandbox::update`] may run. Anything beyond that is dropped so a slow
+    /// frame can't snowball into ever longer frames.
+    pub fn set_max_ticks_per_update(&mut self, max_ticks: u32) {
+        self.max_ticks_per_update = max_ticks.max(1);
+    }
+
+    pub fn parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// Chooses whether the chunks of a tick phase are updated on the rayon thread pool or one after another. Both
+    /// produce identical worlds.
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    pub fn kill_plane(&self) -> Option<isize> {
+        self.kill_plane
+    }
+
+    /// Removes cells and particles that move below `y`, the ones already below it right away. Without a kill plane or
+    /// void edge nothing is ever removed for falling too far, cells just keep falling at their terminal velocity.
+    pub fn set_kill_plane(&mut self, y: Option<isize>) {
+        self.kill_plane = y;
+        self.enforce_kill_plane();
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    /// Limits the world to `bounds`, or makes it unbounded again. Cells beyond walls and voids are removed right away,
+    /// the ones beyond wrapping edges are moved in at the opposite side.
+    pub fn set_bounds(&mut self, bounds: Option<Bounds>) {
+        self.bounds = bounds;
+        self.enforce_bounds();
+    }
+
+    pub fn gravity(&self) -> Vec2 {
+        self.forces.gravity
+    }
+
+    /// Changes the direction and strength of gravity everywhere outside of gravity fields. Cells settle towards the
+    /// nearest of the eight directions, a zero vector leaves them floating.
+    pub fn set_gravity(&mut self, gravity: Vec2) {
+        self.forces.gravity = gravity;
+        self.wake_where(|_| true);
+    }
+
+    pub fn force_fields(&self) -> &[ForceField] {
+        &self.forces.fields
+    }
+
+    /// Places a force field. Fields apply in the order they were added.
+    pub fn add_force_field(&mut self, field: ForceField) {
+        self.forces.fields.push(field);
+        self.wake_where(|pos| field.shape.contains(pos));
+    }
+
+    pub fn remove_force_field(&mut self, idx: usize) -> Option<ForceField> {
+        if idx >= self.forces.fields.len() {
+            return None;
+        }
+        let field = self.forces.fields.remove(idx);
+        self.wake_where(|pos| field.shape.contains(pos));
+        Some(field)
     }
 
-    pub fn grid_pos_from_world_pos(world_pos: Vec3) -> GridPos {
-        let x = Self::to_grid_coord(world_pos.x);
-        let y = Self::to_grid_coord(world_pos.y);
-        (x, y)
+    /// Starts recording [`CellChange`]s. They accumulate until drained with [`Sandbox::take_changes`].
+    pub fn track_changes(&mut self) {
+        self.changes.get_or_insert_with(Vec::new);
     }
 
-    pub fn draw(&mut self) {
-        self.mesh_instance.draw();
+    pub fn take_changes(&mut self) -> Vec<CellChange> {
+        self.changes.as_mut().map(std::mem::take).unwrap_or_default()
     }
 
+    pub fn cells(&self) -> impl Iterator<Item = (GridPos, &Cell)> {
+        self.grid.iter()
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.grid.len()
+    }
+
+    /// Cells that are currently flying outside the grid.
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// The cell at `pos`, positions beyond wrapping edges look at the opposite side of a bounded world.
     pub fn get_cell(&self, pos: GridPos) -> Option<&Cell> {
-        self.grid.get(&pos)
+        self.grid.get(&self.resolve(pos)?)
     }
 
+    /// Whether `pos` is taken, the space beyond walls always is.
     pub fn occupied(&self, pos: &GridPos) -> bool {
-        self.grid.contains_key(pos)
+        match self.bounds.map_or(Place::Inside(*pos), |bounds| bounds.place(*pos)) {
+            Place::Inside(pos) => self.grid.contains(&pos),
+            Place::Wall => true,
+            Place::Void => false,
+        }
     }
 
+    /// Places a new cell at `pos`, nothing is placed beyond walls and voids.
     pub fn insert_cell(&mut self, pos: GridPos, cell_kind: CellKind) {
-        if self.occupied(&pos) {
-            return;
+        if let Some(pos) = self.resolve(pos) {
+            CellStore::insert_cell(self, pos, cell_kind);
         }
-        let cell = Cell::new(cell_kind, self.mesh_instance.instance_count());
-        self.grid.insert(pos, cell);
-        self.active_cells.push(pos);
-        self.add_instance(&pos, &cell);
     }
 
     pub fn remove_cell(&mut self, pos: GridPos) -> Option<Cell> {
-        if let Some(cell) = self.grid.remove(&pos) {
-            self.active_cells.retain(|&p| p != pos);
-            self.mesh_instance.remove_instance(cell.idx);
-            for neighbour in self.get_neighbourins(&pos) {
-                if let Some(neighbour_cell) = self.grid.get_mut(&neighbour) {
-                    if !neighbour_cell.sleeping {
+        CellStore::remove_cell(self, self.resolve(pos)?)
+    }
+
+    pub fn move_cell(&mut self, from: &GridPos, to: &GridPos) {
+        if let (Some(from), Some(to)) = (self.resolve(*from), self.resolve(*to)) {
+            CellStore::move_cell(self, from, to);
+        }
+    }
+
+    pub fn swap_cells(&mut self, pos1: &GridPos, pos2: GridPos) {
+        if let (Some(pos1), Some(pos2)) = (self.resolve(*pos1), self.resolve(pos2)) {
+            CellStore::swap_cells(self, pos1, pos2);
+        }
+    }
+
+    pub fn change_cell_kind(&mut self, pos: GridPos, new_kind: CellKind) {
+        if let Some(pos) = self.resolve(pos) {
+            CellStore::change_cell_kind(self, pos, new_kind);
+        }
+    }
+
+    /// Blows up everything within `radius` of `center`. The blast fades from `force` at the center to nothing at the
+    /// radius: cells that can't withstand it are destroyed or turn into their debris, explosives among them go off
+    /// as well, and loose cells that survive get thrown outwards, leaving the grid as particles when thrown hard enough.
+    /// Everything around the blast is woken up.
+    pub fn explode(&mut self, center: GridPos, radius: f32, force: f32) {
+        assert!(
+            radius > 0.0 && radius <= MAX_BLAST_RADIUS,
+            "Blast radius must be positive and at most {MAX_BLAST_RADIUS}."
+        );
+        assert!(force.is_finite(), "Blast force must be finite.");
+        let mut pending = VecDeque::from([(center, radius, force)]);
+        while let Some((center, radius, force)) = pending.pop_front() {
+            let reach = radius.ceil() as isize;
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let pos = (center.0 + dx, center.1 + dy);
+                    let offset = Vec2::new(dx as f32, dy as f32);
+                    let distance = offset.length();
+                    let Some(&cell) = self.get(pos).filter(|_| distance <= radius) else {
+                        continue;
+                    };
+                    let strength = force * (1.0 - distance / radius);
+
+                    let material = &self.materials[cell.kind];
+                    if strength > material.resistance {
+                        match (material.explosion, material.debris) {
+                            (Some(explosion), _) => {
+                                self.remove_cell(pos);
+                                pending.push_back((pos, explosion.radius, explosion.force));
+                                continue;
+                            }
+                            (None, Some(debris)) => self.change_cell_kind(pos, debris),
+                            (None, None) => {
+                                self.remove_cell(pos);
+                                continue;
+                            }
+                        }
+                    }
+
+                    let direction = if distance > 0.0 { offset / distance } else { Vec2::Y };
+                    let Some(cell) = self.grid.get_mut(&pos) else {
                         continue;
+                    };
+                    let material = &self.materials[cell.kind];
+                    if material.solid {
+                        continue;
+                    }
+                    cell.velocity += direction * strength;
+                    let thrown = strength > THROW_SPEED && !material.gas;
+                    if thrown && let Some(cell) = self.remove_cell(pos) {
+                        self.particles.push(Particle::new(pos, cell));
                     }
-                    neighbour_cell.wake();
-                    self.active_cells.push(neighbour);
                 }
             }
-            let keys: Vec<GridPos> = self.grid.keys().cloned().collect();
-            for key in keys {
-                if let Some(c) = self.grid.get_mut(&key) {
-                    if c.idx > cell.idx {
-                        c.idx -= 1; // Adjust indices of remaining cells
-                    }
+
+            let reach = reach + WAKE_RADIUS;
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    self.wake((center.0 + dx, center.1 + dy));
                 }
             }
-            return Some(cell);
         }
-        None
     }
 
-    pub fn move_cell(&mut self, from: &GridPos, to: &GridPos) {
-        if let Some(cell) = self.grid.remove(from) {
-            self.grid.insert(*to, cell);
-            self.active_cells.retain(|&p| p != *from);
-            self.active_cells.push(*to);
-            let instance = self.mesh_instance.get_instance(cell.idx).expect("Instance not found");
-            let mut transform = instance.as_transform();
-            transform.translation = Vec3::new(to.0 as f32 * GRID_SIZE, to.1 as f32 * GRID_SIZE, 0.0);
+    /// Advances the simulation by `dt` seconds, running as many fixed-length ticks as are owed.
+    pub fn update(&mut self, dt: f64) -> TickReport {
+        assert!(dt >= 0.0 && dt.is_finite(), "Update time must be non-negative and finite.");
+        self.time_since_last_update += dt;
 
-            self.mesh_instance.update_instance_transform(cell.idx, transform);
+        let mut report = TickReport::default();
+        while self.time_since_last_update >= self.tick_duration {
+            if report.ticks == self.max_ticks_per_update {
+                report.skipped = (self.time_since_last_update / self.tick_duration) as u32;
+                self.time_since_last_update %= self.tick_duration;
+                break;
+            }
+            self.tick();
+            self.time_since_last_update -= self.tick_duration;
+            report.ticks += 1;
         }
+
+        report.leftover = self.time_since_last_update;
+        report.alpha = self.time_since_last_update / self.tick_duration;
+        report
     }
 
-    pub fn swap_cells(&mut self, pos1: &GridPos, pos2: GridPos) {
-        let cell1_kind = self.grid.get(pos1).map(|c| c.kind);
-        let cell2_kind = self.grid.get(&pos2).map(|c| c.kind);
+    /// Runs a single simulation tick.
+    ///
+    /// Awake chunks and chunks with expiring cells are updated in [`PHASES`] passes. Every chunk of a pass gets
+    /// exclusive ownership of the 3x3 chunks around it and its own RNG stream derived from the sandbox RNG, so
+    /// running a pass in parallel gives exactly the same result as running it serially. Explosions set off during the
+    /// passes go off afterwards, then cells that got too fast for the grid fly on as [`Particle`]s, both in a fixed
+    /// order.
+    pub fn tick(&mut self) {
+        self.tick += 1;
+        let seed = self.rng.next_u64();
+        let dt = self.tick_duration as f32;
+        let edges = Edges::new(self.bounds, &self.grid);
+
+        let due = self.grid.due_chunks(self.tick);
+        let resting: HashMap<ChunkPos, DirtyRect> = due
+            .iter()
+            .filter_map(|chunk_pos| Some((*chunk_pos, self.grid.chunk(chunk_pos)?.resting_area(self.tick)?)))
+            .collect();
+        let mut phases: [Vec<ChunkPos>; PHASES] = Default::default();
+        for chunk_pos in due {
+            phases[Region::phase(chunk_pos)].push(chunk_pos);
+        }
 
-        if cell1_kind.is_none() || cell2_kind.is_none() {
-            return; // One of the cells does not exist
+        let mut explosions = Vec::new();
+        for centers in phases {
+            let mut regions: Vec<Region> = centers
+                .into_iter()
+                .map(|center| {
+                    Region::extract(
+                        &mut self.grid,
+                        center,
+                        &resting,
+                        &self.materials,
+                        seed,
+                        self.tick,
+                        self.changes.is_some(),
+                    )
+                })
+                .collect();
+
+            if self.parallel {
+                regions.par_iter_mut().for_each(|region| region.update(&self.forces, dt, &edges, self.kill_plane));
+            } else {
+                regions.iter_mut().for_each(|region| region.update(&self.forces, dt, &edges, self.kill_plane));
+            }
+
+            for region in regions {
+                let (changes, particles, region_explosions) = region.restore(&mut self.grid);
+                if let Some(all_changes) = &mut self.changes {
+                    all_changes.extend(changes);
+                }
+                self.particles.extend(particles);
+                explosions.extend(region_explosions);
+            }
+        }
+
+        for (pos, explosion) in explosions {
+            self.explode(pos, explosion.radius, explosion.force);
         }
 
-        self.change_cell_kind(*pos1, cell2_kind.unwrap());
-        self.change_cell_kind(pos2, cell1_kind.unwrap());
+        self.update_particles(dt);
+        self.enforce_bounds();
     }
 
-    pub fn change_cell_kind(&mut self, pos: GridPos, new_kind: CellKind) {
-        if let Some(cell) = self.grid.get_mut(&pos) {
-            cell.kind = new_kind;
-            self.mesh_instance.update_instance_color(cell.idx, &new_kind.color());
+    // ----------------< Private >----------------
+    /// Moves the particles and puts the ones that landed back into the grid.
+    fn update_particles(&mut self, dt: f32) {
+        let mut particles = std::mem::take(&mut self.particles);
+        particles.retain_mut(|particle| {
+            if self.kill_plane.is_some_and(|y| particle.pos.y < y as f32) {
+                return false;
+            }
+            if let Some(bounds) = self.bounds {
+                let here = particle.grid_pos();
+                let Place::Inside(pos) = bounds.place(here) else {
+                    return false;
+                };
+                particle.pos += Vec2::new((pos.0 - here.0) as f32, (pos.1 - here.1) as f32);
+            }
+            let bounds = self.bounds;
+            let walled = |pos| bounds.is_some_and(|bounds| bounds.walled(pos));
+            let occupied = |pos| self.grid.contains(&pos) || walled(pos);
+            let acceleration = self.forces.at(particle.grid_pos()) * dt;
+            let Some(pos) = particle.update(occupied, walled, &self.materials, acceleration) else {
+                return true;
+            };
+            let spot = match bounds {
+                Some(bounds) if self.grid.contains(&pos) => self.nearest_free(bounds, pos),
+                _ => Some(pos),
+            };
+            let Some(pos) = spot else {
+                return true; // Buried without room around, keeps trying
+            };
+            self.land(pos, particle.cell);
+            false
+        });
+        self.particles = particles;
+    }
+
+    /// Where `pos` lies inside the bounds, `None` beyond walls and voids.
+    fn resolve(&self, pos: GridPos) -> Option<GridPos> {
+        match self.bounds.map_or(Place::Inside(pos), |bounds| bounds.place(pos)) {
+            Place::Inside(pos) => Some(pos),
+            Place::Wall | Place::Void => None,
         }
     }
 
-    pub fn update(&mut self, dt: f64) {
-        self.time_since_last_update += dt;
-        if self.time_since_last_update < UPDATE_RATE {
+    /// Removes the cells that got beyond walls and voids and moves the ones that left through a wrapping edge in at
+    /// the opposite side. A cell whose spot there got taken during the tick goes to the nearest free one instead.
+    fn enforce_bounds(&mut self) {
+        let Some(bounds) = self.bounds else {
             return;
+        };
+        let mut outside: Vec<GridPos> = self
+            .grid
+            .chunks()
+            .filter(|&(&chunk_pos, _)| {
+                let origin = ChunkGrid::chunk_origin(chunk_pos);
+                !bounds.contains(origin) || !bounds.contains((origin.0 + CHUNK_SIZE - 1, origin.1 + CHUNK_SIZE - 1))
+            })
+            .flat_map(|(&chunk_pos, chunk)| {
+                let origin = ChunkGrid::chunk_origin(chunk_pos);
+                chunk.iter().map(move |(idx, _)| {
+                    let local = Chunk::local_pos(idx);
+                    (origin.0 + local.0, origin.1 + local.1)
+                })
+            })
+            .filter(|&pos| !bounds.contains(pos))
+            .collect();
+        outside.sort_unstable(); // Chunk order isn't deterministic
+
+        for pos in outside {
+            let Place::Inside(wrapped) = bounds.place(pos) else {
+                CellStore::remove_cell(self, pos);
+                continue;
+            };
+            let Some(to) = self.nearest_free(bounds, wrapped) else {
+                continue; // Waits for the next tick
+            };
+            CellStore::move_cell(self, pos, to);
+            self.wake(to);
+            self.wake_neighbours(to);
         }
-        self.time_since_last_update -= UPDATE_RATE;
+    }
 
-        // let keys: Vec<GridPos> = self.grid.keys().cloned().collect();
-        let keys = self.active_cells.clone();
+    fn enforce_kill_plane(&mut self) {
+        let Some(y) = self.kill_plane else {
+            return;
+        };
+        let mut below: Vec<GridPos> = self.grid.iter().map(|(pos, _)| pos).filter(|pos| pos.1 < y).collect();
+        below.sort_unstable();
+        for pos in below {
+            CellStore::remove_cell(self, pos);
+        }
+        self.particles.retain(|particle| particle.pos.y >= y as f32);
+    }
 
-        for pos in keys {
-            let cell = self.grid.get(&pos).expect("Cell not found");
-            if cell.momentum.abs() > MOMENTUM_THRESHOLD {
-                self.remove_cell(pos);
-                continue; // Skip cells with too much momentum
-            }
-            let update = cell.update(pos, |p| self.get_cell(p), GRAVITY * UPDATE_RATE as f32);
-            if !update.updated {
-                if let Some(cell) = self.grid.get_mut(&pos) {
-                    cell.sleep();
-                    if cell.sleeping {
-                        self.active_cells.retain(|&p| p != pos);
-                    }
-                }
-                continue;
+    /// The free spot inside `bounds` closest to `pos`, searching up to `WRAP_SEARCH` cells away.
+    fn nearest_free(&self, bounds: Bounds, pos: GridPos) -> Option<GridPos> {
+        (0..=WRAP_SEARCH).find_map(|radius| {
+            let ring = (-radius..=radius)
+                .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+                .filter(|&(dx, dy)| dx.abs().max(dy.abs()) == radius);
+            ring.map(|(dx, dy)| (pos.0 + dx, pos.1 + dy))
+                .filter(|&spot| bounds.contains(spot) && !self.grid.contains(&spot))
+                .min_by_key(|&spot| ((spot.0 - pos.0).pow(2) + (spot.1 - pos.1).pow(2), spot))
+        })
+    }
+
+    /// Wakes every cell whose position passes `filter`, e.g. after the forces on it changed.
+    fn wake_where<F>(&mut self, filter: F)
+    where
+        F: Fn(Vec2) -> bool,
+    {
+        let positions: Vec<GridPos> =
+            self.grid.iter().map(|(pos, _)| pos).filter(|pos| filter(Vec2::new(pos.0 as f32, pos.1 as f32))).collect();
+        for pos in positions {
+            self.wake(pos);
+        }
+    }
+
+    fn land(&mut self, pos: GridPos, cell: Cell) {
+        self.insert(pos, cell);
+        self.record(CellChange::Inserted(pos, cell));
+        self.wake(pos);
+        self.wake_neighbours(pos);
+    }
+}
+
+impl CellStore for Sandbox {
+    fn get(&self, pos: GridPos) -> Option<&Cell> {
+        self.grid.get(&pos)
+    }
+
+    fn get_mut(&mut self, pos: GridPos) -> Option<&mut Cell> {
+        self.grid.get_mut(&pos)
+    }
+
+    fn insert(&mut self, pos: GridPos, cell: Cell) -> Option<Cell> {
+        self.grid.insert(pos, cell)
+    }
+
+    fn remove(&mut self, pos: GridPos) -> Option<Cell> {
+        self.grid.remove(&pos)
+    }
+
+    fn wake(&mut self, pos: GridPos) -> bool {
+        self.grid.wake(&pos)
+    }
+
+    fn rng(&mut self) -> &mut SimRng {
+        &mut self.rng
+    }
+
+    fn materials(&self) -> &Materials {
+        &self.materials
+    }
+
+    fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    fn record(&mut self, change: CellChange) {
+        if let Some(changes) = &mut self.changes {
+            changes.push(change);
+        }
+    }
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bounds, Cell, CellKind, GridPos, Materials, Sandbox};
+    use crate::sandbox::Edge;
+
+    fn kind(sandbox: &Sandbox, name: &str) -> CellKind {
+        sandbox.materials().id(name).unwrap()
+    }
+
+    /// A pile of sand and water spanning several chunks, dropped onto a stone floor.
+    fn spill(sandbox: &mut Sandbox, width: isize) {
+        let (sand, water, stone) = (kind(sandbox, "sand"), kind(sandbox, "water"), kind(sandbox, "stone"));
+        for x in -width..width {
+            sandbox.insert_cell((x, 0), stone);
+            for y in 40..60 {
+                sandbox.insert_cell((x, y), if x % 3 == 0 { water } else { sand });
             }
+        }
+    }
 
-            if update.swapped {
-                self.swap_cells(&pos, update.new_pos.unwrap());
-            } else if let Some(transition) = update.transition {
-                if transition.target == TransitionTarget::This {
-                    self.change_cell_kind(pos, transition.result);
-                } else if let Some(new_pos) = update.new_pos {
-                    self.change_cell_kind(new_pos, transition.result);
-                }
-                if transition.remove {
-                    self.remove_cell(pos);
-                }
-            } else if let Some(new_pos) = update.new_pos {
-                self.move_cell(&pos, &new_pos);
+    fn snapshot(sandbox: &Sandbox) -> Vec<(GridPos, Cell)> {
+        let mut cells: Vec<_> = sandbox.cells().map(|(pos, cell)| (pos, *cell)).collect();
+        cells.sort_unstable_by_key(|(pos, _)| *pos);
+        cells
+    }
+
+    #[test]
+    fn runs_headless() {
+        let mut sandbox = Sandbox::with_seed(1);
+        let (sand, stone) = (kind(&sandbox, "sand"), kind(&sandbox, "stone"));
+        for x in -16..16 {
+            sandbox.insert_cell((x, 0), stone);
+            for y in 20..30 {
+                sandbox.insert_cell((x, y), sand);
             }
+        }
 
-            if let Some(new_pos) = update.new_pos
-                && let Some(cell) = self.grid.get_mut(&new_pos)
-            {
-                cell.momentum = update.new_momentum;
-                cell.wake();
-                // wake up neighbours
-                for neighbour in self.get_neighbourins(&new_pos) {
-                    if let Some(neighbour_cell) = self.grid.get_mut(&neighbour) {
-                        if !neighbour_cell.sleeping {
-                            continue;
-                        }
-                        neighbour_cell.wake();
-                        self.active_cells.push(neighbour);
-                    }
+        for _ in 0..100 {
+            sandbox.tick();
+        }
+
+        assert_eq!(sandbox.cell_count(), 32 * 11);
+        assert!(sandbox.cells().filter(|(_, cell)| cell.kind == sand).all(|(pos, _)| pos.1 < 20));
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let worlds: Vec<_> = [false, true]
+            .into_iter()
+            .map(|parallel| {
+                let mut sandbox = Sandbox::with_seed(3);
+                sandbox.set_parallel(parallel);
+                spill(&mut sandbox, 200);
+                for _ in 0..200 {
+                    sandbox.tick();
                 }
-            }
+                snapshot(&sandbox)
+            })
+            .collect();
 
-            if let Some(cell) = self.grid.get_mut(&pos) {
-                cell.wake();
-                // wake up neighbours
-                for neighbour in self.get_neighbourins(&pos) {
-                    if let Some(neighbour_cell) = self.grid.get_mut(&neighbour) {
-                        if !neighbour_cell.sleeping {
-                            continue;
-                        }
-                        neighbour_cell.wake();
-                        self.active_cells.push(neighbour);
-                    }
+        assert_eq!(worlds[0], worlds[1]);
+    }
+
+    #[test]
+    fn same_seed_gives_same_world() {
+        let worlds: Vec<_> = [7, 7, 8]
+            .into_iter()
+            .map(|seed| {
+                let mut sandbox = Sandbox::with_seed(seed);
+                spill(&mut sandbox, 40);
+                for _ in 0..200 {
+                    sandbox.tick();
                 }
+                snapshot(&sandbox)
+            })
+            .collect();
+
+        assert_eq!(worlds[0], worlds[1]);
+        assert_ne!(worlds[0], worlds[2]);
+    }
+
+    #[test]
+    fn restored_rng_state_replays_the_world() {
+        let (mut original, mut replay) = (Sandbox::with_seed(7), Sandbox::with_seed(8));
+        for _ in 0..25 {
+            replay.tick();
+        }
+        spill(&mut original, 40);
+        spill(&mut replay, 40);
+        replay.restore_rng_state(original.rng_state());
+        assert_eq!(replay.rng_state(), original.rng_state());
+
+        for _ in 0..200 {
+            original.tick();
+            replay.tick();
+        }
+
+        // Shades were drawn before the state was restored, everything after it has to line up.
+        let layout =
+            |sandbox: &Sandbox| snapshot(sandbox).into_iter().map(|(pos, cell)| (pos, cell.kind)).collect::<Vec<_>>();
+        assert_eq!(layout(&original), layout(&replay));
+        assert_eq!(original.rng_state(), replay.rng_state());
+    }
+
+    #[test]
+    fn resting_fire_keeps_spreading() {
+        // Without heat, the fuse only burns down through the embers spreading fire
+        let materials = Materials::parse(
+            r#"
+            [[material]]
+            name = "ember"
+            palette = [[1.0, 0.4, 0.0]]
+            conductivity = 0.0
+            burning = true
+
+            [[material]]
+            name = "fuse"
+            palette = [[0.5, 0.3, 0.2]]
+            solid = true
+            conductivity = 0.0
+            ignition = { chance = 0.02, into = "ember" }
+            "#,
+        )
+        .unwrap();
+        let mut sandbox = Sandbox::with_seed(5).with_materials(materials);
+        let (ember, fuse) = (kind(&sandbox, "ember"), kind(&sandbox, "fuse"));
+        sandbox.insert_cell((0, 0), ember);
+        for x in 1..8 {
+            sandbox.insert_cell((x, 0), fuse);
+        }
+
+        for _ in 0..2000 {
+            sandbox.tick();
+        }
+
+        assert!(sandbox.cells().all(|(_, cell)| cell.kind == ember));
+    }
+
+    #[test]
+    fn blasts_throw_loose_cells_as_particles() {
+        let mut sandbox = Sandbox::with_seed(2);
+        sandbox.set_bounds(Some(Bounds::new((-32, 0), (32, 128), Edge::Wall)));
+        let sand = kind(&sandbox, "sand");
+        for x in -32..32 {
+            for y in 0..8 {
+                sandbox.insert_cell((x, y), sand);
             }
         }
+
+        sandbox.explode((0, 4), 12.0, 20.0);
+        let survivors = sandbox.cell_count() + sandbox.particles().len();
+        assert!(!sandbox.particles().is_empty());
+
+        for _ in 0..200 {
+            sandbox.tick();
+        }
+
+        assert!(sandbox.particles().is_empty());
+        assert_eq!(sandbox.cell_count(), survivors);
     }
 
-    // ----------------< Private >----------------
-    fn add_instance(&mut self, pos: &GridPos, cell: &Cell) {
-        let transform = Transform::from_translation(Vec3::new(
-            pos.0 as f32 * GRID_SIZE as f32,
-            pos.1 as f32 * GRID_SIZE as f32,
-            0.0,
-        ))
-        .with_scale(Vec3::splat(GRID_SIZE as f32));
+    #[test]
+    #[should_panic(expected = "Blast radius")]
+    fn blasts_need_a_radius() {
+        Sandbox::with_seed(1).explode((0, 0), 0.0, 10.0);
+    }
+
+    #[test]
+    fn kill_plane_sweeps_resting_cells() {
+        let mut sandbox = Sandbox::with_seed(1);
+        let stone = kind(&sandbox, "stone");
+        for y in -8..8 {
+            sandbox.insert_cell((0, y), stone);
+        }
+        for _ in 0..20 {
+            sandbox.tick();
+        }
+
+        sandbox.set_kill_plane(Some(0));
 
-        self.mesh_instance.add_instance(InstanceData::new(transform, cell.kind.color()));
+        assert_eq!(sandbox.cell_count(), 8);
+        assert!(sandbox.cells().all(|(pos, _)| pos.1 >= 0));
     }
 
-    fn to_grid_coord(value: f32) -> isize {
-        if value < 0.0 { (value / GRID_SIZE - 0.5) as isize } else { (value / GRID_SIZE + 0.5) as isize }
+    #[test]
+    #[should_panic(expected = "Tick rate")]
+    fn tick_rate_must_be_finite() {
+        Sandbox::with_seed(1).set_tick_rate(f64::INFINITY);
     }
 
-    fn get_neighbourins(&self, pos: &GridPos) -> Vec<GridPos> {
-        let mut neighbours = Vec::new();
-        for dx in -3..=3 {
-            for dy in -3..=3 {
-                if dx == 0 && dy == 0 {
-                    continue; // Skip the current cell
-                }
-                let neighbour_pos = (pos.0 + dx, pos.1 + dy);
-                if self.grid.contains_key(&neighbour_pos) {
-                    neighbours.push(neighbour_pos);
-                }
-            }
+    #[test]
+    fn update_catches_up_on_owed_ticks() {
+        let mut sandbox = Sandbox::with_seed(1);
+        sandbox.set_tick_rate(4.0);
+
+        let report = sandbox.update(0.125);
+        assert_eq!((report.ticks, report.skipped, report.leftover, report.alpha), (0, 0, 0.125, 0.5));
+        let report = sandbox.update(0.875);
+        assert_eq!((report.ticks, report.skipped, report.leftover, report.alpha), (4, 0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn update_drops_ticks_beyond_the_limit() {
+        let mut sandbox = Sandbox::with_seed(1);
+        sandbox.set_tick_rate(4.0);
+        sandbox.set_max_ticks_per_update(2);
+
+        let report = sandbox.update(1.375);
+        assert_eq!((report.ticks, report.skipped, report.leftover, report.alpha), (2, 3, 0.125, 0.5));
+        let report = sandbox.update(0.125);
+        assert_eq!((report.ticks, report.skipped, report.leftover, report.alpha), (1, 0, 0.0, 0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Update time")]
+    fn update_time_must_be_finite() {
+        Sandbox::with_seed(1).update(f64::NAN);
+    }
+
+    #[test]
+    fn heat_crosses_chunk_borders_right_away() {
+        let mut sandbox = Sandbox::with_seed(1);
+        let (stone, sand, lava) = (kind(&sandbox, "stone"), kind(&sandbox, "sand"), kind(&sandbox, "lava"));
+        for pos in [(63, 0), (63, -1), (64, -1), (65, -1), (65, 0)] {
+            sandbox.insert_cell(pos, stone);
         }
-        neighbours
+        for _ in 0..30 {
+            sandbox.tick();
+        }
+
+        // The chunk on the left is updated first, but only far away from the border
+        sandbox.insert_cell((4, 30), sand);
+        sandbox.insert_cell((64, 0), lava);
+        sandbox.tick();
+
+        assert!(sandbox.get_cell((63, 0)).unwrap().temperature > 20.0);
     }
 }
@@ -0,0 +1,143 @@
+use rand::Rng;
+
+use crate::sandbox::{
+    cell::{Cell, CellKind},
+    material::Materials,
+    rng::SimRng,
+    sandbox::{CellChange, GridPos},
+};
+
+pub const WAKE_RADIUS: isize = 3;
+
+/// Cell access shared by the whole world and by the regions that are updated on worker threads.
+///
+/// Implementors provide raw storage, randomness and change recording; the grid operations built on top of them
+/// live here so both sides behave identically.
+pub trait CellStore {
+    fn get(&self, pos: GridPos) -> Option<&Cell>;
+
+    fn get_mut(&mut self, pos: GridPos) -> Option<&mut Cell>;
+
+    fn insert(&mut self, pos: GridPos, cell: Cell) -> Option<Cell>;
+
+    fn remove(&mut self, pos: GridPos) -> Option<Cell>;
+
+    /// Wakes the cell at `pos` and flags its chunk for the next tick. Returns `false` if there is no cell.
+    fn wake(&mut self, pos: GridPos) -> bool;
+
+    fn rng(&mut self) -> &mut SimRng;
+
+    fn materials(&self) -> &Materials;
+
+    /// Number of the most recent tick.
+    fn tick(&self) -> u64;
+
+    /// The clock value of the most recent tick. Cells stamped with it are not updated again during that tick.
+    fn clock(&self) -> u8 {
+        self.tick() as u8
+    }
+
+    fn record(&mut self, change: CellChange);
+
+    /// Picks a random palette entry of `kind`.
+    fn random_shade(&mut self, kind: CellKind) -> u8 {
+        let palette_len = self.materials()[kind].palette.len();
+        self.rng().random_range(0..palette_len) as u8
+    }
+
+    /// Rolls the tick a new cell of `kind` expires at, if its material has a limited lifetime.
+    fn random_expiry(&mut self, kind: CellKind) -> Option<u64> {
+        let (min, max) = self.materials()[kind].lifetime?.ticks;
+        let life = self.rng().random_range(min..=max);
+        Some(self.tick() + life as u64)
+    }
+
+    fn occupied(&self, pos: GridPos) -> bool {
+        self.get(pos).is_some()
+    }
+
+    fn wake_neighbours(&mut self, pos: GridPos) {
+        for dx in -WAKE_RADIUS..=WAKE_RADIUS {
+            for dy in -WAKE_RADIUS..=WAKE_RADIUS {
+                if dx == 0 && dy == 0 {
+                    continue; // Skip the current cell
+                }
+                let neighbour = (pos.0 + dx, pos.1 + dy);
+                if self.get(neighbour).is_some_and(|cell| cell.sleeping) {
+                    self.wake(neighbour);
+                }
+            }
+        }
+    }
+
+    fn insert_cell(&mut self, pos: GridPos, cell_kind: CellKind) {
+        if self.occupied(pos) {
+            return;
+        }
+        let shade = self.random_shade(cell_kind);
+        let mut cell = Cell::new(cell_kind, shade);
+        cell.temperature = self.materials()[cell_kind].temperature;
+        cell.moisture = self.materials()[cell_kind].moisture;
+        cell.expires = self.random_expiry(cell_kind);
+        cell.clock = self.clock();
+        self.insert(pos, cell);
+        self.record(CellChange::Inserted(pos, cell));
+    }
+
+    /// Inserts a cell into the first free spot around `pos`, trying the ones above first. Returns `false` if
+    /// everything around is occupied.
+    fn spawn_near(&mut self, pos: GridPos, cell_kind: CellKind) -> bool {
+        const SPOTS: [GridPos; 8] = [(0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0), (-1, -1), (1, -1), (0, -1)];
+
+        let Some(spot) = SPOTS.iter().map(|o| (pos.0 + o.0, pos.1 + o.1)).find(|&spot| !self.occupied(spot)) else {
+            return false;
+        };
+        self.insert_cell(spot, cell_kind);
+        self.wake_neighbours(spot);
+        true
+    }
+
+    fn remove_cell(&mut self, pos: GridPos) -> Option<Cell> {
+        let cell = self.remove(pos)?;
+        self.record(CellChange::Removed(pos));
+        self.wake_neighbours(pos);
+        Some(cell)
+    }
+
+    fn move_cell(&mut self, from: GridPos, to: GridPos) {
+        if let Some(cell) = self.remove(from) {
+            self.insert(to, cell);
+            self.record(CellChange::Moved(from, to));
+        }
+    }
+
+    /// Exchanges two cells, including all of their state.
+    fn swap_cells(&mut self, pos1: GridPos, pos2: GridPos) {
+        let (Some(&cell1), Some(&cell2)) = (self.get(pos1), self.get(pos2)) else {
+            return; // One of the cells does not exist
+        };
+
+        self.insert(pos1, cell2);
+        self.insert(pos2, cell1);
+        self.record(CellChange::Updated(pos1, cell2));
+        self.record(CellChange::Updated(pos2, cell1));
+    }
+
+    fn change_cell_kind(&mut self, pos: GridPos, new_kind: CellKind) {
+        let Some(&cell) = self.get(pos) else {
+            return;
+        };
+        let shade = self.random_shade(new_kind);
+        let expires = self.random_expiry(new_kind);
+
+        let mut cell = cell;
+        cell.kind = new_kind;
+        cell.shade = shade;
+        cell.expires = expires;
+        cell.charged = None;
+        cell.moisture = self.materials()[new_kind].moisture;
+        cell.contact = 0;
+        self.insert(pos, cell);
+        self.record(CellChange::Updated(pos, cell));
+    }
+}
@@ -1,3 +1,7 @@
+mod color;
+
+pub use color::Color;
+
 pub trait Flattenable<T> {
     fn flatten(self) -> Vec<T>;
 }
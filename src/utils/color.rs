@@ -37,6 +37,19 @@ impl Color {
         Self::rgba_u8(r, g, b, a)
     }
 
+    /// The colour with its RGB channels scaled down by `amount`, from 0 (unchanged) to 1 (black).
+    pub fn darkened(&self, amount: f32) -> Self {
+        let scale = 1.0 - amount.clamp(0.0, 1.0);
+        Self { r: self.r * scale, g: self.g * scale, b: self.b * scale, a: self.a }
+    }
+
+    /// The colour with its RGB channels moved towards white by `amount`, from 0 (unchanged) to 1 (white).
+    pub fn lightened(&self, amount: f32) -> Self {
+        let amount = amount.clamp(0.0, 1.0);
+        let lighten = |c: f32| c + (1.0 - c) * amount;
+        Self { r: lighten(self.r), g: lighten(self.g), b: lighten(self.b), a: self.a }
+    }
+
     pub fn as_array(&self) -> [f32; 4] {
         [self.r, self.g, self.b, self.a]
     }